@@ -14,6 +14,7 @@ pub struct Assets {
     /// This is called texture list because the original ReCwar called it that.
     /// It's actually just a list of map surfaces, not all images/textures.
     pub texture_list: String,
+    pub bot_profiles: String,
     /// List of paths to maps supported by bots.
     pub bot_map_paths: Vec<String>,
     /// Map path -> map data as a string.
@@ -76,6 +77,7 @@ impl Assets {
         }
 
         let texture_list = String::from_utf8(asset!("data/texture_list.txt")).unwrap();
+        let bot_profiles = String::from_utf8(asset!("data/bot_profiles.txt")).unwrap();
 
         let mut bot_map_paths = Vec::new();
         let mut maps = FnvHashMap::default();
@@ -220,6 +222,7 @@ impl Assets {
 
         Self {
             texture_list,
+            bot_profiles,
             bot_map_paths,
             maps,
             map_names_to_paths,
@@ -1,6 +1,6 @@
 //! Native and WASM versions using the macroquad engine.
 
-use std::path::Path;
+use std::{io, path::Path};
 
 use cvars_console_macroquad::MacroquadConsole;
 use macroquad::prelude::*;
@@ -8,6 +8,8 @@ use time::{format_description, OffsetDateTime};
 
 use crate::{
     debug::{self, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD},
+    delta::{Changes, DeltaState},
+    interpolation::Snapshot,
     net::{self, Connection},
     prelude::*,
 };
@@ -67,8 +69,44 @@ pub struct ClientGame {
 
     pub notifications: Vec<Notification>,
 
+    /// Recent chat lines, oldest first.
+    pub chat: Vec<Notification>,
+    /// The chat message being typed, None when not typing.
+    pub chat_line: Option<String>,
+    /// Last name sent to the server, changing `cl_name1` sends it again.
+    pub name1: String,
+
+    /// Center of the free camera while the local player is observing.
+    pub observer_pos: Vec2f,
+
     /// Last received server fps and durations info. Might be a few frames old.
     pub server_timings: CommonTimings,
+
+    /// Last vehicle preference sent to the server.
+    pub vehicle_preference: VehiclePreference,
+
+    /// Sequence number of the last input sent to the server.
+    pub input_seq: u32,
+    /// Inputs the server hasn't acknowledged yet,
+    /// they're replayed on top of each correction.
+    pub pending_inputs: VecDeque<PendingInput>,
+
+    /// Received updates, oldest first, remote entities are rendered between them.
+    pub snapshots: VecDeque<Snapshot>,
+    /// Estimated difference between server and client game time.
+    pub server_time_offset: Option<f64>,
+
+    /// Frame number of the last received update, sent back as an acknowledgement.
+    pub last_update: Option<usize>,
+    /// Full states of recent updates, they're the baselines for delta encoding.
+    pub delta_states: VecDeque<DeltaState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PendingInput {
+    pub seq: u32,
+    pub net_input: NetInput,
+    pub dt: f64,
 }
 
 #[derive(Debug)]
@@ -108,7 +146,24 @@ impl Client {
 
             notifications: Vec::new(),
 
+            chat: Vec::new(),
+            chat_line: None,
+            name1: cvars.cl_name1.clone(),
+
+            observer_pos: map.maxs() / 2.0,
+
             server_timings: CommonTimings::default(),
+
+            vehicle_preference: cvars.cl_vehicle,
+
+            input_seq: 0,
+            pending_inputs: VecDeque::new(),
+
+            snapshots: VecDeque::new(),
+            server_time_offset: None,
+
+            last_update: None,
+            delta_states: VecDeque::new(),
         };
 
         dbg_logf!("Window inner size: {}x{}", screen_width(), screen_height());
@@ -188,9 +243,11 @@ impl Client {
         let mut ctx = self.ctx(cvars);
         ctx.sys_net_send();
         ctx.sys_net_receive();
+        self.observer_camera(cvars);
         if !self.cg.paused {
             let dt_update = self.real_time_delta * cvars.d_speed;
             self.gamelogic(cvars, dt_update);
+            self.ctx(cvars).sys_interpolation();
         }
 
         let end = macroquad::time::get_time();
@@ -269,6 +326,9 @@ impl Client {
         ctx.sys_net_send();
         ctx.sys_net_receive();
 
+        ctx.sys_net_send_input();
+        ctx.sys_prediction();
+
         ctx.sys_debug_examples(v!(25 300));
 
         dbg_textf!("vehicle count: {}", self.gs.vehicles.len());
@@ -290,15 +350,88 @@ impl Client {
             self.last_key = Some(key_code);
         }
 
+        if self.cg.chat_line.is_some() {
+            self.chat_input(cvars);
+            return;
+        }
+
         self.cg.input1_prev = self.cg.input1;
         self.cg.input1 = get_input1();
         self.cg.input2_prev = self.cg.input2;
         self.cg.input2 = get_input2();
 
+        if !self.cg.input1_prev.chat && self.cg.input1.chat {
+            // Throw away everything typed while playing, including the key which opened chat.
+            get_chars_pressed();
+            self.cg.chat_line = Some(String::new());
+            self.chat_input(cvars);
+            return;
+        }
+
         if !self.cg.input1_prev.pause && self.cg.input1.pause {
             let msg = ClientMessage::Pause;
             self.ctx(cvars).net_send(msg);
         }
+
+        if !self.cg.input1_prev.observe && self.cg.input1.observe {
+            let player = &self.gs.players[self.cg.tmp_local_player_handle];
+            let msg = if player.state == PlayerState::Observing {
+                ClientMessage::Join
+            } else {
+                ClientMessage::Observe
+            };
+            self.ctx(cvars).net_send(msg);
+        }
+    }
+
+    /// Fly the free camera around, works even while paused.
+    fn observer_camera(&mut self, cvars: &Cvars) {
+        let player = &self.gs.players[self.cg.tmp_local_player_handle];
+        if player.state != PlayerState::Observing || self.cg.chat_line.is_some() {
+            return;
+        }
+
+        let input = self.cg.input1.merged(self.cg.input2);
+        let dir = Vec2f::new(
+            input.right as i32 as f64 - input.left as i32 as f64,
+            input.down as i32 as f64 - input.up as i32 as f64,
+        );
+        let pos = self.cg.observer_pos + dir * cvars.cl_observer_speed * self.real_time_delta;
+        self.cg.observer_pos = pos.clamped(Vec2f::zero(), self.map.maxs());
+    }
+
+    /// Type into the chat line instead of controlling the vehicle.
+    fn chat_input(&mut self, cvars: &Cvars) {
+        // Release all keys so the vehicle doesn't keep doing what it was doing when chat opened.
+        // Chat stays held so the Enter which sends the message doesn't open chat again.
+        self.cg.input1_prev = self.cg.input1;
+        self.cg.input1 = ClientInput {
+            chat: true,
+            ..ClientInput::empty()
+        };
+        self.cg.input2_prev = self.cg.input2;
+        self.cg.input2 = ClientInput::empty();
+
+        let line = self.cg.chat_line.as_mut().unwrap();
+        for c in get_chars_pressed() {
+            if !c.is_control() && line.chars().count() < cvars.cl_chat_length_max {
+                line.push(c);
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            line.pop();
+        }
+
+        if is_key_pressed(KeyCode::Escape) {
+            self.cg.chat_line = None;
+        } else if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            let line = self.cg.chat_line.take().unwrap();
+            let text = line.trim();
+            if !text.is_empty() {
+                let msg = ClientMessage::Chat(text.to_owned());
+                self.ctx(cvars).net_send(msg);
+            }
+        }
     }
 
     pub fn post_render(&mut self, cvars: &Cvars) {
@@ -380,21 +513,141 @@ impl ClientFrameCtx<'_> {
         self.cg.notifications.retain(|notification| {
             self.gs.game_time - notification.start_time < self.cvars.hud_notifications_duration
         });
+        self.cg
+            .chat
+            .retain(|line| self.gs.game_time - line.start_time < self.cvars.hud_chat_duration);
     }
 
     pub fn sys_net_send(&mut self) {
+        if self.cg.name1 != self.cvars.cl_name1 {
+            self.cg.name1 = self.cvars.cl_name1.clone();
+            let connect = Connect {
+                name1: self.cg.name1.clone(),
+                name2: None,
+                vehicle: self.cvars.cl_vehicle,
+            };
+            self.net_send(ClientMessage::Connect(connect));
+        }
+        if self.cg.vehicle_preference != self.cvars.cl_vehicle {
+            self.cg.vehicle_preference = self.cvars.cl_vehicle;
+            let msg = ClientMessage::VehiclePreference(self.cvars.cl_vehicle);
+            self.net_send(msg);
+        }
+    }
+
+    /// Send this frame's input and remember it until the server acknowledges it.
+    pub fn sys_net_send_input(&mut self) {
         // LATER Separate players
 
         let input = self.cg.input1.merged(self.cg.input2);
-
         let net_input = input.to_net_input();
-        let msg = ClientMessage::Input(net_input);
-        self.net_send(msg);
+
+        self.cg.input_seq += 1;
+        let seq = self.cg.input_seq;
+        self.cg.pending_inputs.push_back(PendingInput {
+            seq,
+            net_input,
+            dt: self.gs.dt,
+        });
+
+        let msg = ClientMessage::SequencedInput {
+            seq,
+            net_input,
+            last_update: self.cg.last_update,
+        };
+        // Lost inputs are covered by the ones after them.
+        self.net_send_unreliable(msg);
+    }
+
+    /// Move our own vehicle with the latest input without waiting for the server.
+    pub fn sys_prediction(&mut self) {
+        let Some(&PendingInput { net_input, dt, .. }) = self.cg.pending_inputs.back() else {
+            return;
+        };
+        if let Some((vehicle_handle, input)) = self.predicted_input(net_input) {
+            self.vehicle_movement(vehicle_handle, input, dt);
+        }
+    }
+
+    /// The local vehicle and the input the server will use to move it,
+    /// None if it shouldn't be predicted.
+    fn predicted_input(&self, net_input: NetInput) -> Option<(Index, NetInput)> {
+        if !self.cvars.cl_prediction || self.gs.match_state != MatchState::Playing {
+            return None;
+        }
+
+        let player_handle = self.cg.tmp_local_player_handle;
+        let vehicle_handle = self.gs.players[player_handle].vehicle?;
+        let vehicle = self.gs.vehicles.get(vehicle_handle)?;
+
+        // Same as on the server. The client doesn't know which missile is being guided
+        // but there can only be one.
+        let guiding = self
+            .gs
+            .projectiles
+            .iter()
+            .any(|(_, proj)| proj.owner == player_handle && proj.weapon == Weapon::Gm);
+        let input = if vehicle.destroyed() {
+            NetInput::empty()
+        } else if guiding {
+            net_input.vehicle_while_guiding()
+        } else {
+            net_input
+        };
+        Some((vehicle_handle, input))
+    }
+
+    /// Replay unacknowledged inputs on top of the server state of our vehicle.
+    ///
+    /// Small differences between the prediction and the corrected state
+    /// are only partially applied each update so the vehicle doesn't jitter.
+    fn reconcile(&mut self, last_input_seq: u32, predicted: Option<(Index, Vec2f, f64)>) {
+        self.cg
+            .pending_inputs
+            .retain(|pending| pending.seq > last_input_seq);
+
+        let Some((vehicle_handle, predicted_pos, predicted_angle)) = predicted else {
+            return;
+        };
+
+        for i in 0..self.cg.pending_inputs.len() {
+            let PendingInput { net_input, dt, .. } = self.cg.pending_inputs[i];
+            if let Some((handle, input)) = self.predicted_input(net_input) {
+                if handle == vehicle_handle {
+                    self.vehicle_movement(vehicle_handle, input, dt);
+                }
+            }
+        }
+
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+        let error = predicted_pos - vehicle.pos;
+        if error.magnitude() >= self.cvars.cl_prediction_snap_distance {
+            return;
+        }
+        let smoothing = self.cvars.cl_prediction_smoothing;
+        let pos = vehicle.pos + error * smoothing;
+        let angle_error = vehicle.angle.delta_angle(predicted_angle);
+        let angle = (vehicle.angle + angle_error * smoothing).rem_euclid(2.0 * PI);
+        if !self.map.is_wall_hitbox(vehicle.hitbox, pos, angle) {
+            vehicle.pos = pos;
+            vehicle.angle = angle;
+        }
     }
 
     pub fn net_send(&mut self, msg: ClientMessage) {
         let net_msg = net::serialize(msg);
         let res = self.cg.conn.send(&net_msg);
+        Self::check_sent(res);
+    }
+
+    /// For messages which make the previous ones obsolete.
+    pub fn net_send_unreliable(&mut self, msg: ClientMessage) {
+        let net_msg = net::serialize(msg);
+        let res = self.cg.conn.send_unreliable(&net_msg);
+        Self::check_sent(res);
+    }
+
+    fn check_sent(res: io::Result<()>) {
         if let Err(e) = res {
             // LATER Not warning, don't exit, return to menu
             dbg_logf!("WARNING: Server disconnected: {}", e);
@@ -406,7 +659,12 @@ impl ClientFrameCtx<'_> {
         let (msgs, closed) = self.cg.conn.receive();
         for msg in msgs {
             match msg {
-                ServerMessage::Version(_) => todo!(),
+                ServerMessage::Version(_) => {
+                    dbg_logf!("WARNING: Server sent redundant version, ignoring")
+                }
+                ServerMessage::Rejected(reason) => {
+                    dbg_logf!("WARNING: Server sent rejection after handshake, ignoring: {reason}")
+                }
                 ServerMessage::Init(_) => {
                     dbg_logf!("WARNING: Server sent redundant init, ignoring")
                 }
@@ -418,6 +676,7 @@ impl ClientFrameCtx<'_> {
                 ServerMessage::SpawnVehicle(init) => self.init_vehicle(init),
                 ServerMessage::SpawnProjectile(init) => self.init_projectile(init),
                 ServerMessage::SpawnExplosion(init) => self.init_explosion(init),
+                ServerMessage::SpawnMine(init) => self.init_mine(init),
 
                 ServerMessage::RailBeam(mut beam) => {
                     beam.start_time = self.gs.game_time; // LATER don't sent start_time from server
@@ -428,15 +687,69 @@ impl ClientFrameCtx<'_> {
                     let player_handle = self.gs.players.slot_to_index(index).unwrap();
                     let name = self.gs.players[player_handle].name.clone();
                     self.remove_player(player_handle);
+                    // The server also sends CowDrop but it might arrive later.
+                    if let Some(cow) = &mut self.gs.cow {
+                        if cow.carrier == Some(player_handle) {
+                            cow.carrier = None;
+                        }
+                    }
                     dbg_logf!("Player {name:?} removed");
-                    // LATER Chat notification
                 }
                 ServerMessage::DestroyProjectile { index } => {
                     // LATER Explosion here instead of SpawnExplosion?
                     let old = self.gs.projectiles.remove_by_slot(index);
                     soft_assert!(old.is_some());
                 }
+                ServerMessage::DestroyMine { index } => {
+                    let old = self.gs.mines.remove_by_slot(index);
+                    soft_assert!(old.is_some());
+                }
                 ServerMessage::Kill(kill) => self.handle_kill(kill),
+                ServerMessage::ProtectionEnd { index } => {
+                    let Some((_handle, vehicle)) = self.gs.vehicles.get_by_slot_mut(index) else {
+                        continue;
+                    };
+                    vehicle.protection_end = self.gs.game_time;
+                }
+                ServerMessage::MatchEnd(match_end) => self.handle_match_end(match_end),
+                ServerMessage::MatchStart(match_start) => self.handle_match_start(match_start),
+                ServerMessage::CowPickup { player } => self.handle_cow_pickup(player),
+                ServerMessage::CowDrop { pos } => {
+                    let cow = self.gs.cow.as_mut().unwrap();
+                    cow.pos = pos;
+                    cow.carrier = None;
+                    cow.drop_time = Some(self.gs.game_time);
+                }
+                ServerMessage::CowCapture { player } => self.handle_cow_capture(player),
+                ServerMessage::CowReturn => self.reset_cow(),
+                ServerMessage::Observation(_) => {
+                    dbg_logf!(
+                        "WARNING: Server sent an observation meant for bot controllers, ignoring"
+                    )
+                }
+                ServerMessage::RenamePlayer { index, name } => {
+                    let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
+                    player.name = name;
+                }
+                ServerMessage::Chat(chat) => self.handle_chat(chat),
+                ServerMessage::PlayerObserve { index } => {
+                    let player_handle = self.gs.players.slot_to_index(index).unwrap();
+                    let player = &self.gs.players[player_handle];
+                    if player_handle == self.cg.tmp_local_player_handle {
+                        // Start observing from where the player was.
+                        if let Some(vehicle) = player.vehicle.and_then(|h| self.gs.vehicles.get(h))
+                        {
+                            self.cg.observer_pos = vehicle.pos;
+                        }
+                    }
+                    self.start_observing(player_handle);
+                }
+                ServerMessage::PlayerJoin { index, team } => {
+                    let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
+                    player.state = PlayerState::Playing;
+                    player.score = Score::default();
+                    player.team = team;
+                }
             }
         }
 
@@ -458,11 +771,14 @@ impl ClientFrameCtx<'_> {
         let Update {
             frame_num,
             game_time,
-            game_time_prev: _, // LATER
-            dt: _,             // LATER
+            game_time_prev,
+            dt,
+            last_input_seq,
+            baseline,
             player_inputs,
             vehicles,
             projectiles,
+            removed,
             debug_texts,
             debug_texts_world,
             debug_shapes,
@@ -473,52 +789,98 @@ impl ClientFrameCtx<'_> {
             dbg_logf!("handle_update f: {} gt: {:.03}", frame_num, game_time);
         }
 
-        for InputUpdate { index, net_input } in player_inputs {
-            let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
+        // Updates can arrive out of order over UDP, older ones are obsolete.
+        if self.cg.last_update.is_some_and(|last| frame_num <= last) {
+            if self.cvars.d_log_updates_cl {
+                dbg_logf!("outdated update f: {frame_num}, ignoring");
+            }
+            return;
+        }
+
+        // Where we predicted our vehicle to be before the server corrects it.
+        let predicted = self.predicted_input(NetInput::empty()).map(|(handle, _)| {
+            let vehicle = &self.gs.vehicles[handle];
+            (handle, vehicle.pos, vehicle.angle)
+        });
+
+        // Reconstruct the full state - anything not included is unchanged since the baseline.
+        let baseline_state = baseline.and_then(|baseline| {
+            let found = self
+                .cg
+                .delta_states
+                .iter()
+                .find(|state| state.frame_num == baseline);
+            if found.is_none() {
+                dbg_logf!("WARNING: missing baseline f: {baseline}");
+            }
+            found
+        });
+        let changes = Changes {
+            inputs: player_inputs,
+            vehicles,
+            projectiles,
+            removed,
+        };
+        let state = DeltaState::from_changes(frame_num, changes, baseline_state);
+
+        // The state can contain entities which we don't know about yet
+        // because their spawn message is delayed (or which no longer exist).
+        for (&index, &net_input) in &state.inputs {
+            let Some((_handle, player)) = self.gs.players.get_by_slot_mut(index) else {
+                continue;
+            };
             player.input_prev = player.input;
             player.input = net_input;
         }
 
-        for VehicleUpdate {
-            index,
-            physics:
-                EntityPhysics {
-                    pos,
-                    vel,
-                    angle,
-                    turn_rate,
-                },
-            turret_angle_current,
-            turret_angle_wanted,
-        } in vehicles
-        {
-            let (_handle, vehicle) = self.gs.vehicles.get_by_slot_mut(index).unwrap();
+        for (&index, update) in &state.vehicles {
+            let EntityPhysics {
+                pos,
+                vel,
+                angle,
+                turn_rate,
+            } = update.physics.to_physics();
+            let Some((_handle, vehicle)) = self.gs.vehicles.get_by_slot_mut(index) else {
+                continue;
+            };
             vehicle.pos = pos;
             vehicle.vel = vel;
             vehicle.angle = angle;
             vehicle.turn_rate = turn_rate;
-            vehicle.turret_angle_current = turret_angle_current;
-            vehicle.turret_angle_wanted = turret_angle_wanted;
-        }
-
-        for ProjectileUpdate {
-            index,
-            physics:
-                EntityPhysics {
-                    pos,
-                    vel,
-                    angle,
-                    turn_rate,
-                },
-        } in projectiles
-        {
-            let (_handle, projectile) = self.gs.projectiles.get_by_slot_mut(index).unwrap();
+            vehicle.turret_angle_current = update.turret_angle_current.to_radians();
+            vehicle.turret_angle_wanted = update.turret_angle_wanted.to_radians();
+        }
+
+        for (&index, physics) in &state.projectiles {
+            let EntityPhysics {
+                pos,
+                vel,
+                angle,
+                turn_rate,
+            } = physics.to_physics();
+            let Some((_handle, projectile)) = self.gs.projectiles.get_by_slot_mut(index) else {
+                continue;
+            };
             projectile.pos = pos;
             projectile.vel = vel;
             projectile.angle = angle;
             projectile.turn_rate = turn_rate;
         }
 
+        // The server only uses newer baselines after we acknowledge this one
+        // and it doesn't remember more than `sv_net_delta_history` updates.
+        let oldest = frame_num.saturating_sub(self.cvars.sv_net_delta_history);
+        let oldest = baseline.map_or(oldest, |baseline| baseline.max(oldest));
+        self.cg
+            .delta_states
+            .retain(|state| state.frame_num >= oldest);
+        self.cg.delta_states.push_back(state);
+        self.cg.last_update = Some(frame_num);
+
+        self.add_snapshot(game_time, game_time_prev, dt);
+
+        self.reconcile(last_input_seq, predicted);
+
         DEBUG_TEXTS.with_borrow_mut(|texts| {
             texts.extend(debug_texts);
         });
@@ -529,7 +891,9 @@ impl ClientFrameCtx<'_> {
             shapes.extend(debug_shapes);
         });
 
-        self.cg.server_timings = server_timings;
+        if let Some(server_timings) = server_timings {
+            self.cg.server_timings = server_timings;
+        }
     }
 
     pub fn handle_kill(&mut self, kill: Kill) {
@@ -572,4 +936,104 @@ impl ClientFrameCtx<'_> {
 
         self.update_score_kill(attacker_handle, victim_handle);
     }
+
+    fn handle_match_end(&mut self, match_end: MatchEnd) {
+        let MatchEnd {
+            scores,
+            team_scores,
+            next_match_time,
+        } = match_end;
+
+        for PlayerScore { index, score } in scores {
+            let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
+            player.score = score;
+        }
+        self.gs.team_scores = team_scores;
+
+        self.gs.match_state = MatchState::Intermission {
+            end_time: next_match_time,
+        };
+    }
+
+    fn handle_match_start(&mut self, match_start: MatchStart) {
+        let MatchStart {
+            time_limit,
+            game_mode,
+            teams,
+        } = match_start;
+
+        self.gs.time_limit = time_limit;
+        self.gs.game_mode = game_mode;
+        self.gs.match_state = MatchState::Playing;
+        self.gs.team_scores = Default::default();
+
+        for (_, player) in self.gs.players.iter_mut() {
+            player.score = Score::default();
+            player.guided_missile = None;
+        }
+        for PlayerTeam { index, team } in teams {
+            let (_handle, player) = self.gs.players.get_by_slot_mut(index).unwrap();
+            player.team = team;
+        }
+        self.gs.projectiles.clear();
+        self.gs.mines.clear();
+        self.cg.rail_beams.clear();
+        self.cg.explosions.clear();
+        self.cg.notifications.clear();
+
+        self.reset_cow();
+    }
+
+    fn handle_chat(&mut self, chat: ChatMessage) {
+        let ChatMessage { sender, text } = chat;
+
+        let (text, color) = match sender {
+            Some(index) => {
+                let (_handle, player) = self.gs.players.get_by_slot(index).unwrap();
+                let color = match player.team {
+                    Some(team) => self.cvars.hud_team_color(team),
+                    None => self.cvars.hud_chat_color,
+                };
+                (format!("{}: {}", player.name, text), color)
+            }
+            None => (text, self.cvars.hud_chat_color_server),
+        };
+        dbg_logf!("Chat: {text}");
+        self.cg
+            .chat
+            .push(Notification::new(text, color, self.gs.game_time));
+    }
+
+    fn handle_cow_pickup(&mut self, player: u32) {
+        let (player_handle, player) = self.gs.players.get_by_slot(player).unwrap();
+        let cow = self.gs.cow.as_mut().unwrap();
+        cow.carrier = Some(player_handle);
+        cow.drop_time = None;
+
+        let color = match player.team {
+            Some(team) => self.cvars.hud_team_color(team),
+            None => self.cvars.hud_notifications_color_kill,
+        };
+        self.cg.notifications.push(Notification::new(
+            format!("{} took the cow", player.name),
+            color,
+            self.gs.game_time,
+        ));
+    }
+
+    fn handle_cow_capture(&mut self, player: u32) {
+        let player_handle = self.gs.players.slot_to_index(player).unwrap();
+        self.update_score_capture(player_handle);
+
+        let player = &self.gs.players[player_handle];
+        let color = match player.team {
+            Some(team) => self.cvars.hud_team_color(team),
+            None => self.cvars.hud_notifications_color_kill,
+        };
+        self.cg.notifications.push(Notification::new(
+            format!("{} captured the cow", player.name),
+            color,
+            self.gs.game_time,
+        ));
+    }
 }
@@ -1,13 +1,23 @@
-use crate::prelude::*;
+use crate::{map::SurfaceKind, prelude::*};
 
 impl FrameCtx<'_> {
     // LATER Init fns are here because we need them before a ClientCtx can be created.
     // They are not common code though, move them somewhere else.
 
     pub fn init_player(&mut self, init: PlayerInit) {
-        let PlayerInit { index, name, score } = init;
+        let PlayerInit {
+            index,
+            name,
+            score,
+            team,
+            observing,
+        } = init;
         let mut player = Player::new(name, ClientType::Local);
         player.score = score;
+        player.team = team;
+        if observing {
+            player.state = PlayerState::Observing;
+        }
         let (_player_handle, old) = self.gs.players.insert_at_slot(index, player);
         assert!(old.is_none());
     }
@@ -26,6 +36,7 @@ impl FrameCtx<'_> {
             turret_angle_current,
             turret_angle_wanted,
             spawn_time,
+            protection_end,
             owner,
         } = init;
 
@@ -35,6 +46,7 @@ impl FrameCtx<'_> {
         vehicle.turn_rate = turn_rate;
         vehicle.turret_angle_current = turret_angle_current;
         vehicle.turret_angle_wanted = turret_angle_wanted;
+        vehicle.protection_end = protection_end;
 
         let (vehicle_handle, _old) = self.gs.vehicles.insert_at_slot(index, vehicle);
 
@@ -66,32 +78,257 @@ impl FrameCtx<'_> {
             explode_time,
             owner,
             target: None, // LATER Simulate homing missiles on client too?
+            hit_vehicles: Vec::new(),
         };
         let (_projectile_handle, old) = self.gs.projectiles.insert_at_slot(index, projectile);
         soft_assert!(old.is_none());
     }
 
+    pub fn init_mine(&mut self, init: MineInit) {
+        let MineInit {
+            index,
+            pos,
+            arm_time,
+            owner,
+        } = init;
+
+        let owner = self.gs.players.slot_to_index(owner).unwrap();
+        let mine = Mine::new(pos, arm_time, owner);
+        let (_mine_handle, old) = self.gs.mines.insert_at_slot(index, mine);
+        soft_assert!(old.is_none());
+    }
+
     pub fn remove_player(&mut self, player_handle: Index) {
+        self.remove_player_entities(player_handle);
+        self.gs.players.remove(player_handle);
+    }
+
+    /// Turn the player into an observer without a vehicle, team or score.
+    pub fn start_observing(&mut self, player_handle: Index) {
+        self.remove_player_entities(player_handle);
+        let player = &mut self.gs.players[player_handle];
+        player.state = PlayerState::Observing;
+        player.vehicle = None;
+        player.guided_missile = None;
+        player.respawn = Respawn::No;
+        player.score = Score::default();
+        player.team = None;
+    }
+
+    fn remove_player_entities(&mut self, player_handle: Index) {
         self.gs
             .projectiles
             .retain(|_, proj| proj.owner != player_handle);
-        // LATER This ignores gs.rail_hits because we're gonna change that anyway.
+        self.gs.mines.retain(|_, mine| mine.owner != player_handle);
         self.gs.vehicles.retain(|_, veh| veh.owner != player_handle);
-        self.gs.players.remove(player_handle);
     }
 
     pub fn update_score_kill(&mut self, attacker_handle: Index, victim_handle: Index) {
+        let victim_team = self.gs.players[victim_handle].team;
         let attacker = &mut self.gs.players[attacker_handle];
         if attacker_handle == victim_handle {
             attacker.score.suicides += 1;
+        } else if attacker.team.is_some() && attacker.team == victim_team {
+            attacker.score.teamkills += 1;
         } else {
             attacker.score.kills += 1;
+            if let Some(team) = attacker.team {
+                self.gs.team_scores[team as usize].kills += 1;
+            }
         }
 
         let victim = &mut self.gs.players[victim_handle];
         victim.score.deaths += 1; // All deaths, including suicides
     }
 
+    pub fn update_score_capture(&mut self, player_handle: Index) {
+        let player = &mut self.gs.players[player_handle];
+        player.score.captures += 1;
+        if let Some(team) = player.team {
+            self.gs.team_scores[team as usize].captures += 1;
+        }
+
+        self.reset_cow();
+    }
+
+    /// Put the cow at its spawn or remove it if the game mode doesn't have one.
+    pub fn reset_cow(&mut self) {
+        self.gs.cow = match self.gs.game_mode {
+            GameMode::Ctc(_) => self.map.cow_spawn().map(Cow::new),
+            GameMode::Ffa(_) | GameMode::Tw(_) => None,
+        };
+    }
+
+    /// Turn, accelerate and move one vehicle with the given input.
+    ///
+    /// Shared so the client can predict its own vehicle.
+    pub fn vehicle_movement(&mut self, vehicle_handle: Index, input: NetInput, dt: f64) {
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+        let mut stats = self.cvars.g_vehicle_movement_stats(vehicle.veh_type);
+        if self.cvars.g_surface_effects {
+            let surface = self.map.surface_at_pos(vehicle.pos);
+            let (friction, speed) = match (surface.kind, vehicle.veh_type) {
+                (SurfaceKind::Water, VehicleType::Hovercraft)
+                    if self.cvars.g_hovercraft_water_immune =>
+                {
+                    (1.0, 1.0)
+                }
+                _ => (surface.friction as f64, surface.speed as f64),
+            };
+            stats = stats.on_surface(friction, speed);
+            if surface.kind == SurfaceKind::Snow {
+                stats.turn_effectiveness *= self.cvars.g_surface_snow_turn_effectiveness;
+            }
+        }
+
+        let new_angle = Self::turning(
+            &stats,
+            &mut vehicle.vel,
+            &vehicle.angle,
+            &mut vehicle.turn_rate,
+            input,
+            dt,
+        );
+
+        // When turning next to a wall, try to push the vehicle away from it
+        // so it doesn't get stuck.
+        if !self
+            .map
+            .is_wall_hitbox(vehicle.hitbox, vehicle.pos, new_angle)
+        {
+            vehicle.angle = new_angle;
+        } else if let Some(new_pos) =
+            self.map
+                .push_out_hitbox(vehicle.hitbox, vehicle.pos, new_angle)
+        {
+            vehicle.pos = new_pos;
+            vehicle.angle = new_angle;
+        } else {
+            vehicle.turn_rate = 0.0;
+        }
+
+        Self::accel_decel(&stats, &mut vehicle.vel, vehicle.angle, input, dt);
+
+        Self::wall_sliding(self.cvars, self.map, vehicle, dt);
+    }
+
+    /// Move the vehicle, sliding along walls instead of bouncing off them.
+    ///
+    /// Each corner of the hitbox is traced separately, the vehicle stops just before the first hit,
+    /// loses the part of its velocity going into the wall and continues with the rest of the frame.
+    /// LATER Wall corners can still poke into the sides of the hitbox between its corners.
+    fn wall_sliding(cvars: &Cvars, map: &Map, vehicle: &mut Vehicle, dt: f64) {
+        let mut remaining = dt;
+        // Usually there's at most one hit per frame, two when sliding into a corner.
+        for _ in 0..3 {
+            let delta = vehicle.vel * remaining;
+            if delta == Vec2f::zero() {
+                return;
+            }
+
+            let hit = vehicle
+                .hitbox
+                .corners(vehicle.pos, vehicle.angle)
+                .into_iter()
+                .filter(|&corner| !map.is_wall(corner))
+                .filter_map(|corner| map.trace_wall(corner, corner + delta))
+                .min_by(|a, b| a.t.total_cmp(&b.t));
+            let Some(hit) = hit else {
+                Self::try_move(map, vehicle, vehicle.pos + delta);
+                return;
+            };
+
+            // Stop a tiny bit before the wall to avoid floating point issues.
+            let t = (hit.t - 0.01 / delta.magnitude()).max(0.0);
+            Self::try_move(map, vehicle, vehicle.pos + delta * t);
+            remaining *= 1.0 - hit.t;
+
+            // Like in the original RecWar, the map edge absorbs the impact.
+            let vel_into_wall = vehicle.vel.dot(hit.normal);
+            if vel_into_wall < 0.0 {
+                let bounce = if hit.map_edge {
+                    0.0
+                } else {
+                    cvars.g_wall_bounce
+                };
+                vehicle.vel -= hit.normal * vel_into_wall * (1.0 + bounce);
+            }
+        }
+    }
+
+    pub fn try_move(map: &Map, vehicle: &mut Vehicle, new_pos: Vec2f) {
+        if !map.is_wall_hitbox(vehicle.hitbox, new_pos, vehicle.angle) {
+            vehicle.pos = new_pos;
+        }
+    }
+
+    pub fn turning(
+        stats: &MovementStats,
+        vel: &mut Vec2f,
+        angle: &f64,
+        turn_rate: &mut f64,
+        input: NetInput,
+        dt: f64,
+    ) -> f64 {
+        let tr_change = input.right_left() * stats.turn_rate_increase * dt;
+        *turn_rate += tr_change;
+
+        // Friction's constant component - always the same no matter the speed
+        let tr_fric_const = stats.turn_rate_friction_const * dt;
+        if *turn_rate >= 0.0 {
+            *turn_rate = (*turn_rate - tr_fric_const).max(0.0);
+        } else {
+            *turn_rate = (*turn_rate + tr_fric_const).min(0.0);
+        }
+
+        // Friction's linear component - increases with speed
+        let tr_new = *turn_rate * (1.0 - stats.turn_rate_friction_linear).powf(dt);
+        *turn_rate = tr_new.clamped(-stats.turn_rate_max, stats.turn_rate_max);
+
+        // A dirty hack to approximate car steering (i.e. no turning when still, reversed when moving backwards).
+        let steering_coef = if stats.steering_car > 0.0 {
+            let sign = angle.to_vec2f().dot(*vel).signum();
+            // Steering when below this speed is less effective.
+            let steering_speed = vel
+                .magnitude()
+                .clamped(-stats.steering_car, stats.steering_car);
+            steering_speed * sign / stats.steering_car
+        } else {
+            1.0
+        };
+
+        // Turning - part of vel gets rotated to simulate steering
+        let turn = *turn_rate * dt * steering_coef;
+        let vel_rotation = turn * stats.turn_effectiveness;
+        vel.rotate_z(vel_rotation);
+
+        // Normalize to 0..=360 deg
+        (angle + turn).rem_euclid(2.0 * PI)
+    }
+
+    pub fn accel_decel(
+        stats: &MovementStats,
+        vel: &mut Vec2f,
+        angle: f64,
+        input: NetInput,
+        dt: f64,
+    ) {
+        let vel_change =
+            (input.up() * stats.accel_forward - input.down() * stats.accel_backward) * dt;
+        *vel += angle.to_vec2f() * vel_change;
+
+        // Friction's constant component - always the same no matter the speed
+        let vel_fric_const = stats.friction_const * dt;
+        let vel_norm = vel.try_normalized().unwrap_or_default();
+        *vel -= (vel_fric_const).min(vel.magnitude()) * vel_norm;
+
+        // Friction's linear component - increases with speed
+        *vel *= (1.0 - stats.friction_linear).powf(dt);
+        if vel.magnitude_squared() > stats.speed_max.powi(2) {
+            *vel = vel_norm * stats.speed_max;
+        }
+    }
+
     pub fn sys_debug_examples(&self, offset: Vec2f) {
         if !self.cvars.d_examples {
             return;
@@ -23,7 +23,43 @@ cvars! {
 
     /// Master switch for AI - disable if you want stationary targets
     ai: bool = true,
-
+    /// How often bots' aim error changes in seconds.
+    ai_aim_error_interval: f64 = 0.5,
+    /// Bots only drive forward when the next waypoint is within this angle, otherwise they turn first.
+    ai_drive_angle_deg: f64 = 60.0,
+    /// Bots fire when their aim is within this angle of the predicted target position.
+    ai_fire_angle_deg: f64 = 5.0,
+    /// Bots launch guided missiles at enemies around corners up to this far along the path.
+    ai_gm_range: f64 = 1000.0,
+    /// How often bots recompute their path in seconds.
+    ai_repath_interval: f64 = 1.0,
+    /// Bots don't notice enemies farther away than this.
+    ai_sight_range: f64 = 800.0,
+    /// Bots don't fire weapons with splash damage when the explosion
+    /// would be closer to them than its radius plus this margin.
+    ai_splash_margin: f64 = 20.0,
+    /// Bots which move less than this distance in `ai_stuck_time` seconds consider themselves stuck.
+    ai_stuck_distance: f64 = 10.0,
+    ai_stuck_time: f64 = 1.0,
+    /// How long stuck bots reverse.
+    ai_unstuck_time: f64 = 0.7,
+    /// Bots move on to the next waypoint when they're this close to its center.
+    ai_waypoint_radius: f64 = 24.0,
+    /// Enemies this close to the target count as a group for cluster bombs.
+    ai_weapon_group_radius: f64 = 100.0,
+    /// How many enemies make a group worth a cluster bomb.
+    ai_weapon_group_size: usize = 2,
+    /// Bots prefer the railgun at this distance and farther.
+    ai_weapon_range_long: f64 = 400.0,
+    /// Bots prefer the machine gun at this distance and closer.
+    ai_weapon_range_short: f64 = 150.0,
+
+    /// Skill of bots between 0 (the first profile in bot_profiles.txt) and 1 (the last).
+    /// Only applies to newly added bots.
+    bots_difficulty: f64 = 0.5,
+    /// Each bot's difficulty is random within this distance of `bots_difficulty`.
+    /// 0 means all bots use the same profile.
+    bots_difficulty_spread: f64 = 0.25,
     /// Final override for the max number of bots
     bots_max: usize = 20,
     /// Desired number of bots based on the number of spawns
@@ -31,8 +67,20 @@ cvars! {
     /// Desired number of bots based on the number of tiles (map size)
     bots_tiles_per_bot: f32 = 100.0,
 
+    /// Max characters in a typed chat message, the server has its own limit.
+    cl_chat_length_max: usize = 150,
+
     cl_cluster_bomb_size: f64 = 1.5,
 
+    /// How long to keep moving remote entities when updates are late.
+    cl_extrapolation_max: f64 = 0.1,
+
+    /// Render remote entities between received updates instead of jumping to each one.
+    cl_interpolation: bool = true,
+    /// How far behind the server remote entities are rendered.
+    /// Must be larger than the usual gap between updates.
+    cl_interpolation_delay: f64 = 0.05,
+
     cl_machine_gun_trail_length: f64 = 10.0,
     cl_machine_gun_trail_thickness: f64 = 1.5,
 
@@ -42,6 +90,23 @@ cvars! {
     cl_net_connect_retry_delay_ms: u64 = 10,
     cl_net_connect_retry_print_every_n: u32 = 100,
     cl_net_server_addr: String = "127.0.0.1:26000".to_owned(),
+    /// Seconds without hearing from the server before giving up (UDP only).
+    cl_net_timeout: f64 = 10.0,
+    /// How to connect to the server - TCP, UDP or WebSocket.
+    cl_net_transport: Transport = Transport::Tcp,
+    /// Server URL when using WebSocket, `cl_net_server_addr` is used by the other transports.
+    cl_net_websocket_url: String = "ws://127.0.0.1:26001".to_owned(),
+
+    /// How fast the free camera moves while observing, in pixels per second.
+    cl_observer_speed: f64 = 800.0,
+
+    /// Move your own vehicle immediately instead of waiting for the server.
+    cl_prediction: bool = true,
+    /// How much of the prediction error is kept after each server correction.
+    /// 0 snaps to the server state, values closer to 1 correct more slowly.
+    cl_prediction_smoothing: f64 = 0.75,
+    /// Larger errors (e.g. after a collision or teleport) snap immediately.
+    cl_prediction_snap_distance: f64 = 50.0,
 
     cl_railgun_trail_duration: f64 = 0.05,
     cl_railgun_trail_thickness: f64 = 1.5,
@@ -49,6 +114,11 @@ cvars! {
     cl_screenshot_path: String = "screenshots/{date_time}--f{frame_num}-gt{game_time}.tga".to_owned(),
     cl_screenshots: bool = false,
 
+    /// Larger differences between the estimated and received server time reset the estimate.
+    cl_server_time_resync: f64 = 0.25,
+    /// How quickly the estimate of server time follows new updates.
+    cl_server_time_smoothing: f64 = 0.05,
+
     cl_spawn_indicator_animation_time: f64 = 0.3,
     cl_spawn_indicator_blinking_period: f64 = 0.3,
     cl_spawn_indicator_duration: f64 = 1.5,
@@ -56,9 +126,15 @@ cvars! {
     cl_spawn_indicator_square_side_end: f32 = 40.0,
     cl_spawn_indicator_thickness: f32 = 2.0,
 
+    cl_spawn_protection_shield_alpha: f32 = 0.6,
+    cl_spawn_protection_shield_thickness: f32 = 2.0,
+
     /// Two player local multiplayer
     cl_splitscreen: bool = false,
 
+    /// Which vehicle to spawn in, the server might not allow it.
+    cl_vehicle: VehiclePreference = VehiclePreference::Random,
+
     con_background_alpha: f32 = 0.8,
     con_height_fraction: f32 = 0.45,
     con_history_line_font_size: f32 = 16.0,
@@ -165,9 +241,24 @@ cvars! {
     g_cluster_bomb_time_spread: f64 = 0.2,
     g_cluster_bomb_vehicle_velocity_factor: f64 = 1.0,
 
+    /// The match ends when a team captures the cow this many times. 0 means no limit.
+    g_ctc_capture_limit: i32 = 5,
+    g_ctc_cow_pickup_radius: f64 = 24.0,
+    /// A dropped cow returns to its spawn if nobody picks it up for this many seconds. 0 means never.
+    g_ctc_cow_return_delay: f64 = 30.0,
+    g_ctc_score_capture: i32 = 5,
+
+    /// The match ends when a player reaches this many kills. 0 means no limit.
+    g_ffa_kill_limit: i32 = 20,
     g_ffa_score_death: i32 = -1,
     g_ffa_score_kill: i32 = 1,
 
+    /// Whether teammates can damage each other. Damaging yourself is always possible.
+    g_friendly_fire: bool = false,
+
+    /// Which game mode to use. Changes take effect when the next match starts.
+    g_game_mode: GameModeKind = GameModeKind::Ffa,
+
     g_guided_missile_accel_forward: f64 = 2000.0,
     g_guided_missile_damage_direct: f64 = 0.0,
     g_guided_missile_explosion_damage: f64 = 56.0, // exact from orig RW
@@ -254,8 +345,6 @@ cvars! {
     g_hardpoint_tank_rockets_x: f64 = 35.0,
     g_hardpoint_tank_rockets_y: f64 = 0.0,
 
-    g_hitcircle_radius: f64 = 24.0, // LATER proper hitbox
-
     g_homing_missile_accel_forward: f64 = 2000.0,
     g_homing_missile_angle_detect: f64 = 40.0f64.to_radians(), // LATER (also other places) use Deg
     g_homing_missile_angle_forget: f64 = 50.0f64.to_radians(),
@@ -278,9 +367,11 @@ cvars! {
 
     g_hovercraft_accel_backward: f64 = 400.0,
     g_hovercraft_accel_forward: f64 = 400.0,
+    g_hovercraft_allowed: bool = true,
     g_hovercraft_armor_scale: f64 = 0.65,
     g_hovercraft_friction_const: f64 = 0.0,
     g_hovercraft_friction_linear: f64 = 0.6,
+    g_hovercraft_mass: f64 = 0.8,
     g_hovercraft_maxs_x: f64 = 22.0,
     g_hovercraft_maxs_y: f64 = 14.0,
     g_hovercraft_mins_x: f64 = -22.0,
@@ -296,12 +387,16 @@ cvars! {
     g_hovercraft_turret_offset_chassis_y: f64 = 5.0,
     g_hovercraft_turret_offset_turret_x: f64 = -8.0,
     g_hovercraft_turret_offset_turret_y: f64 = 0.0,
+    /// Hovercraft float over water so the water's friction and speed don't apply.
+    g_hovercraft_water_immune: bool = true,
 
     g_hummer_accel_backward: f64 = 600.0,
     g_hummer_accel_forward: f64 = 600.0,
+    g_hummer_allowed: bool = true,
     g_hummer_armor_scale: f64 = 0.625,
     g_hummer_friction_const: f64 = 11.0,
     g_hummer_friction_linear: f64 = 0.8,
+    g_hummer_mass: f64 = 1.0,
     g_hummer_maxs_x: f64 = 20.0,
     g_hummer_maxs_y: f64 = 9.0,
     g_hummer_mins_x: f64 = -20.0,
@@ -318,6 +413,9 @@ cvars! {
     g_hummer_turret_offset_turret_x: f64 = 0.0,
     g_hummer_turret_offset_turret_y: f64 = 0.0,
 
+    /// How long the final scoreboard is shown before the next match starts.
+    g_intermission_duration: f64 = 10.0,
+
     g_machine_gun_angle_spread: f64 = 0.015,
     g_machine_gun_damage: f64 = 2.5, // exact from orig RW
     g_machine_gun_refire: f64 = 0.050,
@@ -329,6 +427,18 @@ cvars! {
     /// The map to play on. Set to empty string for random.
     g_map: String = "".to_owned(),
 
+    /// How long after being dropped a mine can be triggered.
+    g_mine_arming_delay: f64 = 1.0,
+    g_mine_explosion_damage: f64 = 75.0,
+    g_mine_explosion_radius: f64 = 40.0,
+    g_mine_explosion_scale: f64 = 1.0,
+    /// Maximum number of mines a vehicle can have on the map at the same time.
+    g_mine_limit: usize = 5,
+    /// How far behind the back of the vehicle the mine is dropped.
+    g_mine_offset_back: f64 = 8.0,
+    /// Distance from the mine at which a vehicle's center triggers it.
+    g_mine_trigger_radius: f64 = 16.0,
+
     g_players_max: usize = 64,
     g_players_min: usize = 4,
 
@@ -356,11 +466,35 @@ cvars! {
     g_self_destruct_explosion_scale: f64 = 2.0, // LATER radius
     g_self_destruct_radius: f64 = 175.0,
 
+    /// Distances (to enemies, to where the player died) above this don't improve a spawn's score.
+    g_spawn_distance_max: f64 = 800.0,
+    /// Spawns with a living vehicle this close are not used unless all of them are occupied.
+    g_spawn_occupied_radius: f64 = 48.0,
+    /// Projectiles this close to a spawn make it less desirable.
+    g_spawn_projectile_radius: f64 = 150.0,
+    /// How long vehicles can't be damaged after spawning. Ends early when the player shoots.
+    g_spawn_protection: f64 = 2.0,
+    g_spawn_weight_death_distance: f64 = 0.5,
+    g_spawn_weight_enemy_distance: f64 = 1.0,
+    /// Penalty for each enemy within `g_spawn_distance_max` with line of sight to the spawn.
+    g_spawn_weight_enemy_visible: f64 = 0.5,
+    /// Penalty for each projectile within `g_spawn_projectile_radius`.
+    g_spawn_weight_projectile: f64 = 0.25,
+    /// Randomness so players don't always spawn in the same few places.
+    g_spawn_weight_random: f64 = 0.3,
+
+    /// Whether surface friction and speed from the texture list affect vehicle movement.
+    g_surface_effects: bool = true,
+    /// Multiplier of how well vehicles' velocity follows their heading on snow.
+    g_surface_snow_turn_effectiveness: f64 = 0.5,
+
     g_tank_accel_backward: f64 = 550.0,
     g_tank_accel_forward: f64 = 550.0,
+    g_tank_allowed: bool = true,
     g_tank_armor_scale: f64 = 1.0,
     g_tank_friction_const: f64 = 50.0,
     g_tank_friction_linear: f64 = 0.9,
+    g_tank_mass: f64 = 1.5,
     g_tank_maxs_x: f64 = 19.0,
     g_tank_maxs_y: f64 = 12.0,
     g_tank_mins_x: f64 = -19.0,
@@ -377,9 +511,31 @@ cvars! {
     g_tank_turret_offset_turret_x: f64 = -14.0,
     g_tank_turret_offset_turret_y: f64 = 0.0,
 
+    /// Match length in seconds. 0 means no limit.
+    g_time_limit: f64 = 600.0,
+
     g_turret_turn_speed_deg: f64 = 120.0,
     g_turret_turn_step_angle_deg: f64 = 45.0,
 
+    /// The match ends when a team reaches this many kills. 0 means no limit.
+    g_tw_kill_limit: i32 = 50,
+    /// Points for killing a teammate
+    g_tw_score_teamkill: i32 = -1,
+
+    /// How much of the relative speed is kept after two vehicles collide.
+    /// 0 means they stop relative to each other, 1 means perfectly elastic.
+    g_vehicle_collision_restitution: f64 = 0.5,
+    /// Whether vehicles collide with each other.
+    g_vehicle_collisions: bool = true,
+    /// Whether wrecks block vehicles. Wrecks are immovable.
+    g_vehicle_collisions_wrecks: bool = false,
+    /// Ignore player preferences and spawn everyone in a random allowed vehicle.
+    g_vehicle_force_random: bool = false,
+
+    /// How much of the speed into a wall is kept after hitting it.
+    /// The map edge always absorbs the whole impact.
+    g_wall_bounce: f64 = 0.1,
+
     /// Original RecWar had 4.
     hud_ammo_height: f64 = 4.0,
     /// Original RecWar had 99.
@@ -387,6 +543,20 @@ cvars! {
     hud_ammo_x: f64 = 30.0,
     hud_ammo_y: f64 = -30.0,
 
+    hud_chat_color: CVec3 = CVec3::WHITE,
+    /// Joins, leaves, renames and other messages from the server.
+    hud_chat_color_server: CVec3 = CVec3::YELLOW,
+    hud_chat_duration: f64 = 10.0,
+    hud_chat_font_size: f64 = 20.0,
+    hud_chat_line_height: f32 = 20.0,
+    hud_chat_lines_max: usize = 6,
+    hud_chat_shadow_alpha: f32 = 1.0,
+    hud_chat_shadow_x: f32 = 1.0,
+    hud_chat_shadow_y: f32 = 1.0,
+    /// Position of the newest line, older ones are above it.
+    hud_chat_x: f64 = 30.0,
+    hud_chat_y: f64 = -110.0,
+
     /// Original RecWar had 9.
     hud_hp_height: f64 = 9.0,
     /// Original RecWar had 99.
@@ -421,6 +591,11 @@ cvars! {
     hud_notifications_y_from_top: f32 = 150.0,
     hud_notifications_y_offset: f32 = -40.0,
 
+    hud_observing_font_size: f64 = 24.0,
+    hud_observing_shadow_x: f32 = 1.0,
+    hud_observing_shadow_y: f32 = 1.0,
+    hud_observing_y: f64 = -50.0,
+
     hud_pause_font_size: f64 = 64.0,
     hud_pause_shadow_x: f32 = 2.0,
     hud_pause_shadow_y: f32 = 2.0,
@@ -456,6 +631,9 @@ cvars! {
     hud_scoreboard_width_name: f32 = 150.0,
     hud_scoreboard_width_points: f32 = 50.0,
 
+    hud_team_color_blue: CVec3 = CVec3::BLUE2,
+    hud_team_color_red: CVec3 = CVec3::RED,
+
     hud_weapon_icon_shadow_alpha: f64 = 0.5,
     hud_weapon_icon_shadow_x: f32 = 2.0,
     hud_weapon_icon_shadow_y: f32 = 2.0,
@@ -466,6 +644,7 @@ cvars! {
     r_align_to_pixels_background: bool = true,
     r_align_to_pixels_text: bool = true,
     r_cluster_bombs: bool = true,
+    r_cow_scale: f32 = 1.0,
     r_explosion_duration: f64 = 0.5,
     // After trying true for a while, I think false looks better:
     // - CB looks smoother. With true it sometimes looked like it had 2 stages
@@ -481,12 +660,59 @@ cvars! {
     r_smoothing: bool = false,
     r_splitscreen_gap: f64 = 8.0,
 
+    /// Number of bots in each headless simulation match.
+    sim_bots: usize = 8,
+    /// Comma separated map paths, simulation matches cycle through them.
+    sim_maps: String = "maps/Atrium.map,maps/Bunkers (2).map,maps/Delta.map".to_owned(),
+    /// How many matches to simulate.
+    sim_matches: usize = 10,
+    sim_results_path: String = "sim_results.txt".to_owned(),
+
     /// LATER fix - Does not work in MQ: https://github.com/not-fl3/macroquad/issues/264
     sv_auto_pause_on_minimize: bool = true,
     /// LATER fix - Does not work in MQ: https://github.com/not-fl3/macroquad/issues/264
     sv_auto_unpause_on_restore: bool = false,
 
+    /// Longer chat messages are cut off.
+    sv_chat_length_max: usize = 150,
+    /// How many chat messages a client can send within `sv_chat_rate_period`, the rest are dropped.
+    sv_chat_rate_messages: usize = 3,
+    /// Seconds of wall clock time.
+    sv_chat_rate_period: f64 = 5.0,
+
+    /// Longer player names are cut off.
+    sv_name_length_max: usize = 32,
+
+    /// Address for external bot controllers, they get `Observation`s instead of the full game state.
+    /// Empty means disabled.
+    sv_net_controller_listen_addr: String = String::new(),
+    /// Send only entities which changed since the last update the client acknowledged.
+    sv_net_delta: bool = true,
+    /// How many sent updates to remember per client as possible baselines.
+    /// Clients which fall further behind get a full update.
+    sv_net_delta_history: usize = 300,
+    /// Seconds a new connection has to send its version before it's dropped.
+    sv_net_handshake_timeout: f64 = 5.0,
+    /// Received inputs waiting to be applied, one per tick. Older ones are dropped.
+    sv_net_input_queue_max: usize = 10,
     sv_net_listen_addr: String = "127.0.0.1:26000".to_owned(),
+    /// Seconds between logging each client's bandwidth, 0 means disabled.
+    sv_net_stats_interval: f64 = 0.0,
+    /// Seconds without hearing from a client before disconnecting it (UDP only).
+    sv_net_timeout: f64 = 10.0,
+    /// Seconds between sending server fps and durations to clients.
+    sv_net_timings_interval: f64 = 0.5,
+    /// Address for clients using UDP, can be the same port as TCP. Empty means disabled.
+    sv_net_udp_listen_addr: String = "127.0.0.1:26000".to_owned(),
+    /// Address for clients using WebSocket (browsers), must be a different port than TCP.
+    /// Empty means disabled.
+    sv_net_websocket_listen_addr: String = "127.0.0.1:26001".to_owned(),
+
+    /// Seconds of wall clock time a client has to wait between observing and joining.
+    sv_observe_interval: f64 = 2.0,
+    /// New players start as observers and join when they're ready.
+    /// Bot controllers always play.
+    sv_observe_on_connect: bool = false,
 
     /// LATER Without extrapolation, this needs to be significantly higher than framerate to avoid judder.
     ///     Assuming rendering at 60 fps:
@@ -686,6 +912,14 @@ impl Cvars {
         }
     }
 
+    pub fn g_vehicle_allowed(&self, veh_type: VehicleType) -> bool {
+        match veh_type {
+            VehicleType::Tank => self.g_tank_allowed,
+            VehicleType::Hovercraft => self.g_hovercraft_allowed,
+            VehicleType::Hummer => self.g_hummer_allowed,
+        }
+    }
+
     pub fn g_vehicle_hitbox(&self, veh_type: VehicleType) -> Hitbox {
         match veh_type {
             VehicleType::Tank => Hitbox {
@@ -712,6 +946,14 @@ impl Cvars {
         self.g_armor * scale
     }
 
+    pub fn g_vehicle_mass(&self, veh_type: VehicleType) -> f64 {
+        match veh_type {
+            VehicleType::Tank => self.g_tank_mass,
+            VehicleType::Hovercraft => self.g_hovercraft_mass,
+            VehicleType::Hummer => self.g_hummer_mass,
+        }
+    }
+
     pub fn g_vehicle_movement_stats(&self, veh_type: VehicleType) -> MovementStats {
         match veh_type {
             VehicleType::Tank => MovementStats {
@@ -898,6 +1140,13 @@ impl Cvars {
         }
     }
 
+    pub fn hud_team_color(&self, team: Team) -> CVec3 {
+        match team {
+            Team::Red => self.hud_team_color_red,
+            Team::Blue => self.hud_team_color_blue,
+        }
+    }
+
     pub fn g_weapon_reload_time(&self, weapon: Weapon) -> f64 {
         match weapon {
             Weapon::Mg => self.g_machine_gun_reload_time,
@@ -909,6 +1158,32 @@ impl Cvars {
             Weapon::Bfg => self.g_bfg_reload_time,
         }
     }
+
+    /// Initial projectile speed, not including the vehicle's velocity.
+    pub fn g_weapon_speed(&self, weapon: Weapon) -> f64 {
+        match weapon {
+            Weapon::Mg => self.g_machine_gun_speed,
+            Weapon::Rail => self.g_railgun_speed,
+            Weapon::Cb => self.g_cluster_bomb_speed,
+            Weapon::Rockets => self.g_rockets_speed,
+            Weapon::Hm => self.g_homing_missile_speed_initial,
+            Weapon::Gm => self.g_guided_missile_speed_initial,
+            Weapon::Bfg => self.g_bfg_speed,
+        }
+    }
+
+    /// How much of the vehicle's velocity is added to the projectile.
+    pub fn g_weapon_vehicle_velocity_factor(&self, weapon: Weapon) -> f64 {
+        match weapon {
+            Weapon::Mg => self.g_machine_gun_vehicle_velocity_factor,
+            Weapon::Rail => self.g_railgun_vehicle_velocity_factor,
+            Weapon::Cb => self.g_cluster_bomb_vehicle_velocity_factor,
+            Weapon::Rockets => self.g_rockets_vehicle_velocity_factor,
+            Weapon::Hm => self.g_homing_missile_vehicle_velocity_factor,
+            Weapon::Gm => self.g_guided_missile_vehicle_velocity_factor,
+            Weapon::Bfg => self.g_bfg_vehicle_velocity_factor,
+        }
+    }
 }
 
 /// Vec3 with support for cvars. Should be converted to Vec3 before use in gamecode.
@@ -983,6 +1258,18 @@ impl From<Color> for CVec3 {
     }
 }
 
+/// The game mode selected by cvars, the actual rules for the current match are in `GameMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum GameModeKind {
+    /// Free For All
+    Ffa,
+    /// Team War
+    Tw,
+    /// Capture The Cow
+    Ctc,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
 #[strum(ascii_case_insensitive)]
 pub enum Hardpoint {
@@ -1015,6 +1302,39 @@ pub enum TickrateMode {
     // to avoid interpolating between death and spawn location.
 }
 
+/// Network protocol used by the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumString, Display)]
+#[strum(ascii_case_insensitive)]
+pub enum Transport {
+    Tcp,
+    Udp,
+    /// The only option in browsers.
+    WebSocket,
+}
+
+/// Which vehicle a player wants to spawn in.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumString, Display, FromRepr, Deserialize, Serialize,
+)]
+#[strum(ascii_case_insensitive)]
+pub enum VehiclePreference {
+    Random,
+    Tank,
+    Hovercraft,
+    Hummer,
+}
+
+impl VehiclePreference {
+    pub fn vehicle_type(self) -> Option<VehicleType> {
+        match self {
+            VehiclePreference::Random => None,
+            VehiclePreference::Tank => Some(VehicleType::Tank),
+            VehiclePreference::Hovercraft => Some(VehicleType::Hovercraft),
+            VehiclePreference::Hummer => Some(VehicleType::Hummer),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MovementStats {
     pub accel_backward: f64,
@@ -1030,4 +1350,24 @@ pub struct MovementStats {
     pub turn_rate_max: f64,
 }
 
+impl MovementStats {
+    /// Adjust the stats for the surface under the vehicle.
+    ///
+    /// Friction is grip - it scales acceleration, how fast the vehicle slows down
+    /// and how well its velocity follows its heading.
+    /// Speed scales acceleration and max speed.
+    pub fn on_surface(mut self, friction: f64, speed: f64) -> Self {
+        self.accel_backward *= friction * speed;
+        self.accel_forward *= friction * speed;
+        self.friction_const *= friction;
+        // Linear friction is applied as `vel *= (1 - friction_linear)^dt`
+        // so scale the exponent instead of the factor to keep it in range.
+        self.friction_linear = 1.0 - (1.0 - self.friction_linear).powf(friction);
+        self.speed_max *= speed;
+        self.turn_effectiveness =
+            (self.turn_effectiveness * friction).min(self.turn_effectiveness.max(1.0));
+        self
+    }
+}
+
 //pub fn load_cvars
@@ -0,0 +1,356 @@
+//! Delta compression and quantization of `Update`s.
+//!
+//! Each update only contains entities (and only their fields) which changed since a baseline -
+//! the last update the client acknowledged.
+//! Both sides remember the full (quantized) state of recent updates
+//! so the client can reconstruct what was left out.
+//!
+//! Physics is quantized to precision that's still far below what can be seen on screen.
+//! The client's prediction and interpolation smooth over the rest.
+
+use crate::prelude::*;
+
+/// Position precision in pixels.
+const POS_STEP: f64 = 1.0 / 16.0;
+/// Turn rate precision in radians per second.
+const TURN_RATE_STEP: f64 = 1.0 / 1000.0;
+
+/// `EntityPhysics` in 16 bytes instead of 48.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuantizedPhysics {
+    pub pos: [i32; 2],
+    /// Whole pixels per second.
+    pub vel: [i16; 2],
+    pub angle: QuantizedAngle,
+    pub turn_rate: i16,
+}
+
+impl QuantizedPhysics {
+    pub fn new(physics: &EntityPhysics) -> Self {
+        let pos = |v: f64| (v / POS_STEP).round() as i32;
+        let vel = |v: f64| v.round().clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        let turn_rate = (physics.turn_rate / TURN_RATE_STEP)
+            .round()
+            .clamp(i16::MIN as f64, i16::MAX as f64) as i16;
+        Self {
+            pos: [pos(physics.pos.x), pos(physics.pos.y)],
+            vel: [vel(physics.vel.x), vel(physics.vel.y)],
+            angle: QuantizedAngle::new(physics.angle),
+            turn_rate,
+        }
+    }
+
+    pub fn to_physics(self) -> EntityPhysics {
+        EntityPhysics {
+            pos: Vec2f::new(self.pos[0] as f64, self.pos[1] as f64) * POS_STEP,
+            vel: Vec2f::new(self.vel[0] as f64, self.vel[1] as f64),
+            angle: self.angle.to_radians(),
+            turn_rate: self.turn_rate as f64 * TURN_RATE_STEP,
+        }
+    }
+}
+
+/// Angle in 1/65536 of a full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct QuantizedAngle(pub u16);
+
+impl QuantizedAngle {
+    pub fn new(angle: f64) -> Self {
+        let turns = angle.rem_euclid(2.0 * PI) / (2.0 * PI);
+        // Rounding up to a full turn wraps around to 0.
+        Self((turns * 65536.0).round() as u32 as u16)
+    }
+
+    pub fn to_radians(self) -> f64 {
+        self.0 as f64 / 65536.0 * 2.0 * PI
+    }
+}
+
+/// Only the fields which changed since the baseline,
+/// all of them if the entity is new.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PhysicsUpdate {
+    pub pos: Option<[i32; 2]>,
+    pub vel: Option<[i16; 2]>,
+    pub angle: Option<QuantizedAngle>,
+    pub turn_rate: Option<i16>,
+}
+
+impl PhysicsUpdate {
+    pub fn new(current: &QuantizedPhysics, baseline: Option<&QuantizedPhysics>) -> Self {
+        Self {
+            pos: field(current.pos, baseline.map(|b| b.pos)),
+            vel: field(current.vel, baseline.map(|b| b.vel)),
+            angle: field(current.angle, baseline.map(|b| b.angle)),
+            turn_rate: field(current.turn_rate, baseline.map(|b| b.turn_rate)),
+        }
+    }
+
+    /// None if a field is missing and there's nothing to take it from.
+    pub fn apply(&self, baseline: Option<&QuantizedPhysics>) -> Option<QuantizedPhysics> {
+        Some(QuantizedPhysics {
+            pos: self.pos.or(baseline.map(|b| b.pos))?,
+            vel: self.vel.or(baseline.map(|b| b.vel))?,
+            angle: self.angle.or(baseline.map(|b| b.angle))?,
+            turn_rate: self.turn_rate.or(baseline.map(|b| b.turn_rate))?,
+        })
+    }
+}
+
+fn field<T: Copy + PartialEq>(current: T, baseline: Option<T>) -> Option<T> {
+    (Some(current) != baseline).then_some(current)
+}
+
+/// Full quantized state of a vehicle as sent in updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VehicleState {
+    pub physics: QuantizedPhysics,
+    pub turret_angle_current: QuantizedAngle,
+    pub turret_angle_wanted: QuantizedAngle,
+}
+
+/// The full state sent (or reconstructed) at one server frame, indexed by slot.
+#[derive(Debug, Clone)]
+pub struct DeltaState {
+    pub frame_num: usize,
+    pub inputs: FnvHashMap<u32, NetInput>,
+    pub vehicles: FnvHashMap<u32, VehicleState>,
+    pub projectiles: FnvHashMap<u32, QuantizedPhysics>,
+}
+
+impl DeltaState {
+    pub fn new(frame_num: usize) -> Self {
+        Self {
+            frame_num,
+            inputs: FnvHashMap::default(),
+            vehicles: FnvHashMap::default(),
+            projectiles: FnvHashMap::default(),
+        }
+    }
+
+    /// Everything that differs from the baseline or everything if there's none.
+    pub fn changed_since(&self, baseline: Option<&DeltaState>) -> Changes {
+        let mut changes = Changes::default();
+
+        for index in sorted_slots(&self.inputs) {
+            let net_input = self.inputs[&index];
+            if baseline.and_then(|b| b.inputs.get(&index)) != Some(&net_input) {
+                changes.inputs.push(InputUpdate { index, net_input });
+            }
+        }
+
+        for index in sorted_slots(&self.vehicles) {
+            let current = &self.vehicles[&index];
+            let base = baseline.and_then(|b| b.vehicles.get(&index));
+            if base != Some(current) {
+                changes.vehicles.push(VehicleUpdate {
+                    index,
+                    physics: PhysicsUpdate::new(&current.physics, base.map(|b| &b.physics)),
+                    turret_angle_current: field(
+                        current.turret_angle_current,
+                        base.map(|b| b.turret_angle_current),
+                    ),
+                    turret_angle_wanted: field(
+                        current.turret_angle_wanted,
+                        base.map(|b| b.turret_angle_wanted),
+                    ),
+                });
+            }
+        }
+
+        for index in sorted_slots(&self.projectiles) {
+            let current = &self.projectiles[&index];
+            let base = baseline.and_then(|b| b.projectiles.get(&index));
+            if base != Some(current) {
+                changes.projectiles.push(ProjectileUpdate {
+                    index,
+                    physics: PhysicsUpdate::new(current, base),
+                });
+            }
+        }
+
+        if let Some(baseline) = baseline {
+            changes.removed = RemovedEntities {
+                inputs: removed_slots(&baseline.inputs, &self.inputs),
+                vehicles: removed_slots(&baseline.vehicles, &self.vehicles),
+                projectiles: removed_slots(&baseline.projectiles, &self.projectiles),
+            };
+        }
+
+        changes
+    }
+
+    /// Reconstruct the full state from the changes and the baseline.
+    ///
+    /// Which entities exist is decided only by the update, not by the client's game state,
+    /// because spawn and removal messages might arrive before or after it.
+    pub fn from_changes(frame_num: usize, changes: Changes, baseline: Option<&DeltaState>) -> Self {
+        let Changes {
+            inputs,
+            vehicles,
+            projectiles,
+            removed,
+        } = changes;
+
+        let mut state = match baseline {
+            Some(baseline) => Self {
+                frame_num,
+                ..baseline.clone()
+            },
+            None => Self::new(frame_num),
+        };
+        for index in removed.inputs {
+            state.inputs.remove(&index);
+        }
+        for index in removed.vehicles {
+            state.vehicles.remove(&index);
+        }
+        for index in removed.projectiles {
+            state.projectiles.remove(&index);
+        }
+
+        for InputUpdate { index, net_input } in inputs {
+            state.inputs.insert(index, net_input);
+        }
+
+        for update in vehicles {
+            let base = baseline.and_then(|b| b.vehicles.get(&update.index));
+            let vehicle = (|| {
+                Some(VehicleState {
+                    physics: update.physics.apply(base.map(|b| &b.physics))?,
+                    turret_angle_current: update
+                        .turret_angle_current
+                        .or(base.map(|b| b.turret_angle_current))?,
+                    turret_angle_wanted: update
+                        .turret_angle_wanted
+                        .or(base.map(|b| b.turret_angle_wanted))?,
+                })
+            })();
+            match vehicle {
+                Some(vehicle) => {
+                    state.vehicles.insert(update.index, vehicle);
+                }
+                None => dbg_logf!("WARNING: incomplete vehicle update {}", update.index),
+            }
+        }
+
+        for update in projectiles {
+            let base = baseline.and_then(|b| b.projectiles.get(&update.index));
+            match update.physics.apply(base) {
+                Some(physics) => {
+                    state.projectiles.insert(update.index, physics);
+                }
+                None => dbg_logf!("WARNING: incomplete projectile update {}", update.index),
+            }
+        }
+
+        state
+    }
+}
+
+/// The entity updates of an `Update`.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub inputs: Vec<InputUpdate>,
+    pub vehicles: Vec<VehicleUpdate>,
+    pub projectiles: Vec<ProjectileUpdate>,
+    pub removed: RemovedEntities,
+}
+
+/// Slots which are in the baseline but no longer exist.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RemovedEntities {
+    pub inputs: Vec<u32>,
+    pub vehicles: Vec<u32>,
+    pub projectiles: Vec<u32>,
+}
+
+/// Keep the messages deterministic.
+fn sorted_slots<T>(map: &FnvHashMap<u32, T>) -> Vec<u32> {
+    let mut slots: Vec<_> = map.keys().copied().collect();
+    slots.sort_unstable();
+    slots
+}
+
+fn removed_slots<T>(baseline: &FnvHashMap<u32, T>, current: &FnvHashMap<u32, T>) -> Vec<u32> {
+    sorted_slots(baseline)
+        .into_iter()
+        .filter(|index| !current.contains_key(index))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantization() {
+        let physics = EntityPhysics {
+            pos: v!(1234.56, -7.89),
+            vel: v!(-300.4 100_000),
+            angle: 2.0 * PI - 0.000_01,
+            turn_rate: -1.2345,
+        };
+        let q = QuantizedPhysics::new(&physics).to_physics();
+        assert!((q.pos - physics.pos).magnitude() <= POS_STEP);
+        assert_eq!(q.vel, v!(-300 32767));
+        assert_eq!(q.angle, 0.0);
+        assert!((q.turn_rate - physics.turn_rate).abs() <= TURN_RATE_STEP);
+
+        let angle = QuantizedAngle::new(-PI / 2.0).to_radians();
+        assert!((angle - PI * 1.5).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_delta_roundtrip() {
+        let moving = NetInput {
+            up: true,
+            ..NetInput::default()
+        };
+        let physics = QuantizedPhysics::new(&EntityPhysics {
+            pos: v!(100 200),
+            vel: v!(50 0),
+            angle: 0.0,
+            turn_rate: 0.0,
+        });
+        let vehicle = VehicleState {
+            physics,
+            turret_angle_current: QuantizedAngle(0),
+            turret_angle_wanted: QuantizedAngle(0),
+        };
+        let mut baseline = DeltaState::new(1);
+        baseline.inputs.insert(0, NetInput::empty());
+        baseline.inputs.insert(1, NetInput::empty());
+        baseline.vehicles.insert(0, vehicle);
+        baseline.projectiles.insert(7, physics);
+
+        let mut current = DeltaState::new(2);
+        current.inputs.insert(0, NetInput::empty());
+        current.inputs.insert(1, moving);
+        let mut moved = vehicle;
+        moved.physics.pos[0] += 16;
+        current.vehicles.insert(0, moved);
+        current.vehicles.insert(1, vehicle);
+
+        // Only the changed input and fields are sent, new entities are sent whole.
+        let changes = current.changed_since(Some(&baseline));
+        assert_eq!(changes.inputs.len(), 1);
+        assert_eq!(changes.inputs[0].index, 1);
+        assert_eq!(changes.vehicles.len(), 2);
+        let update = changes.vehicles[0];
+        assert_eq!(update.physics.pos, Some(moved.physics.pos));
+        assert_eq!(update.physics.vel, None);
+        assert_eq!(update.turret_angle_current, None);
+        assert_eq!(changes.vehicles[1].physics.apply(None), Some(physics));
+
+        assert_eq!(changes.removed.projectiles, [7]);
+
+        let full = current.changed_since(None);
+        assert_eq!(full.inputs.len(), 2);
+
+        let reconstructed = DeltaState::from_changes(2, changes, Some(&baseline));
+        assert_eq!(reconstructed.inputs, current.inputs);
+        assert_eq!(reconstructed.vehicles, current.vehicles);
+        assert!(reconstructed.projectiles.is_empty());
+    }
+}
@@ -25,10 +25,15 @@ pub struct Player {
     pub input_prev: NetInput,
     pub respawn: Respawn,
     pub death_time: f64,
+    pub death_pos: Option<Vec2f>,
     pub vehicle: Option<Index>,
     pub guided_missile: Option<Index>,
     pub cur_weapon: Weapon,
     pub score: Score,
+    /// None in game modes without teams.
+    pub team: Option<Team>,
+    /// Used on the next respawn.
+    pub vehicle_preference: VehiclePreference,
 }
 
 impl Player {
@@ -41,10 +46,13 @@ impl Player {
             input_prev: NetInput::empty(),
             respawn: Respawn::No,
             death_time: 0.0,
+            death_pos: None,
             vehicle: None,
             guided_missile: None,
             cur_weapon: Weapon::Mg,
             score: Score::default(),
+            team: None,
+            vehicle_preference: VehiclePreference::Random,
         }
     }
 }
@@ -79,14 +87,56 @@ pub enum Respawn {
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Score {
+    /// Kills of enemies, not including suicides and teamkills.
     pub kills: i32,
     pub deaths: i32,
     pub suicides: i32,
+    pub teamkills: i32,
+    pub captures: i32,
 }
 
 impl Score {
     pub fn points(&self, cvars: &Cvars) -> i32 {
-        self.kills * cvars.g_ffa_score_kill + self.deaths * cvars.g_ffa_score_death
+        self.kills * cvars.g_ffa_score_kill
+            + self.deaths * cvars.g_ffa_score_death
+            + self.teamkills * cvars.g_tw_score_teamkill
+            + self.captures * cvars.g_ctc_score_capture
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRepr, Deserialize, Serialize)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+/// Score of the whole team.
+///
+/// Kept separately from player scores so it doesn't change when players leave.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TeamScore {
+    pub kills: i32,
+    pub captures: i32,
+}
+
+/// The cow in Capture The Cow.
+#[derive(Debug, Clone)]
+pub struct Cow {
+    /// When carried, this is the last known position of the carrier.
+    pub pos: Vec2f,
+    /// Handle to the player carrying the cow.
+    pub carrier: Option<Index>,
+    /// When the cow was dropped, None if it's carried or at its spawn.
+    pub drop_time: Option<f64>,
+}
+
+impl Cow {
+    pub fn new(pos: Vec2f) -> Self {
+        Self {
+            pos,
+            carrier: None,
+            drop_time: None,
+        }
     }
 }
 
@@ -110,18 +160,79 @@ impl Notification {
 #[derive(Debug, Clone)]
 pub struct Ai {
     pub player: Index,
-    pub movement: i32,
-    pub turning: i32,
-    pub firing: bool,
+    pub profile: BotProfile,
+    /// A newly visible enemy and when the bot first saw it.
+    /// It becomes the target after the profile's reaction time.
+    pub spotted: Option<(Index, f64)>,
+    /// Handle to the vehicle the bot is trying to kill.
+    pub target: Option<Index>,
+    /// Handle to the vehicle the bot's path leads to, can be behind walls.
+    pub hunted: Option<Index>,
+    /// Tiles left to drive through, the first is the next waypoint.
+    pub path: Vec<Vec2u>,
+    pub next_repath_time: f64,
+    /// Tiles left for the guided missile to fly through, the first is the next waypoint.
+    pub gm_path: Vec<Vec2u>,
+    pub next_gm_repath_time: f64,
+    /// Where the bot was when it last checked whether it's stuck.
+    pub stuck_check_pos: Vec2f,
+    pub stuck_check_time: f64,
+    /// Game time until which the bot reverses to get unstuck.
+    pub reverse_end: f64,
+    /// How far off the bot's aim currently is in radians.
+    pub aim_error: f64,
+    pub next_aim_error_time: f64,
+    /// Game time when the bot can press a turret key again.
+    pub next_turret_time: f64,
 }
 
 impl Ai {
-    pub fn new(player_index: Index) -> Self {
+    pub fn new(player_index: Index, profile: BotProfile) -> Self {
         Self {
             player: player_index,
-            movement: 0,
-            turning: 0,
-            firing: false,
+            profile,
+            spotted: None,
+            target: None,
+            hunted: None,
+            path: Vec::new(),
+            next_repath_time: 0.0,
+            gm_path: Vec::new(),
+            next_gm_repath_time: 0.0,
+            stuck_check_pos: Vec2f::zero(),
+            stuck_check_time: 0.0,
+            reverse_end: 0.0,
+            aim_error: 0.0,
+            next_aim_error_time: 0.0,
+            next_turret_time: 0.0,
+        }
+    }
+}
+
+/// How well a bot plays, loaded from `data/bot_profiles.txt`.
+#[derive(Debug, Clone)]
+pub struct BotProfile {
+    pub name: String,
+    /// Seconds between an enemy becoming visible and the bot reacting to it.
+    pub reaction_time: f64,
+    /// Standard deviation of the aim error in degrees.
+    pub aim_error_deg: f64,
+    /// Minimum seconds between turret turning steps.
+    pub turret_interval: f64,
+    /// Between 0 and 1 - how eagerly the bot hunts enemies and how close it gets to them.
+    pub aggression: f64,
+    /// Favorite weapons first.
+    pub weapons: Vec<Weapon>,
+}
+
+impl Default for BotProfile {
+    fn default() -> Self {
+        Self {
+            name: "default".to_owned(),
+            reaction_time: 0.4,
+            aim_error_deg: 5.0,
+            turret_interval: 0.25,
+            aggression: 0.5,
+            weapons: vec![Weapon::Mg, Weapon::Rockets],
         }
     }
 }
@@ -144,6 +255,8 @@ pub struct Vehicle {
     pub ammos: Vec<Ammo>,
     /// Game time when this vehicle was spawned.
     pub spawn_time: f64,
+    /// Game time until which this vehicle can't be damaged.
+    pub protection_end: f64,
     /// Index of the player who owns this vehicle.
     pub owner: Index,
     /// Indices of homing missiles targeting this vehicle.
@@ -183,6 +296,7 @@ impl Vehicle {
             hp_fraction: 1.0,
             ammos,
             spawn_time,
+            protection_end: spawn_time + cvars.g_spawn_protection,
             owner,
             hms: Vec::new(),
         }
@@ -191,9 +305,13 @@ impl Vehicle {
     pub fn destroyed(&self) -> bool {
         self.hp_fraction <= 0.0
     }
+
+    pub fn protected(&self, game_time: f64) -> bool {
+        game_time < self.protection_end
+    }
 }
 
-#[derive(Debug, Clone, Copy, FromRepr, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, FromRepr, Deserialize, Serialize)]
 pub enum VehicleType {
     Tank,
     Hovercraft,
@@ -228,10 +346,43 @@ pub struct Projectile {
     pub owner: Index,
     /// If this is a homing projectile, this is the handle of the target vehicle.
     pub target: Option<Index>,
+    /// Handles of vehicles this projectile already hit.
+    ///
+    /// Prevents piercing projectiles (rails) from hitting the same vehicle twice
+    /// when one segment ends inside the hitbox and the next starts inside it the next frame.
+    /// A respawned vehicle gets a new handle so it can be hit again if it's unlucky enough to respawn in the path.
+    /// Other projectiles get removed on hit so this stays empty for them.
+    pub hit_vehicles: Vec<Index>,
+}
+
+/// A mine dropped behind a vehicle.
+///
+/// Not a weapon because it's available to all vehicles in addition to their current weapon.
+#[derive(Debug, Clone)]
+pub struct Mine {
+    pub pos: Vec2f,
+    /// Game time after which the mine can be triggered.
+    pub arm_time: f64,
+    /// Handle of the player who dropped this mine.
+    pub owner: Index,
+    /// The owner's vehicle doesn't trigger the mine until it leaves the trigger radius
+    /// so the mine doesn't go off under a vehicle that's not moving.
+    pub owner_left: bool,
+}
+
+impl Mine {
+    pub fn new(pos: Vec2f, arm_time: f64, owner: Index) -> Self {
+        Self {
+            pos,
+            arm_time,
+            owner,
+            owner_left: false,
+        }
+    }
 }
 
 /// Weapon type - currently hardcoded.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumCount, FromRepr, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumCount, FromRepr, Deserialize, Serialize)]
 pub enum Weapon {
     Mg,
     Rail,
@@ -242,6 +393,15 @@ pub enum Weapon {
     Bfg,
 }
 
+/// What dealt damage to a vehicle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DamageCause {
+    /// Direct hits, explosions and BFG beams.
+    Weapon(Weapon),
+    Mine,
+    SelfDestruct,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Hitbox {
     pub mins: Vec2f,
@@ -256,4 +416,166 @@ impl Hitbox {
         let back_right = pos + Vec2f::new(self.mins.x, self.maxs.y).rotated_z(angle);
         [back_left, front_left, front_right, back_right]
     }
+
+    /// Collision of two oriented hitboxes using the separating axis theorem.
+    ///
+    /// Returns the collision normal pointing from `self` towards `other`
+    /// and the penetration depth along it or None if the hitboxes don't overlap.
+    pub fn collision(
+        self,
+        pos: Vec2f,
+        angle: f64,
+        other: Hitbox,
+        other_pos: Vec2f,
+        other_angle: f64,
+    ) -> Option<(Vec2f, f64)> {
+        let corners = self.corners(pos, angle);
+        let other_corners = other.corners(other_pos, other_angle);
+
+        // Rectangles only have 2 unique axes each.
+        let axes = [
+            angle.to_vec2f(),
+            (angle + PI / 2.0).to_vec2f(),
+            other_angle.to_vec2f(),
+            (other_angle + PI / 2.0).to_vec2f(),
+        ];
+
+        let project = |corners: &[Vec2f; 4], axis: Vec2f| {
+            corners
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), corner| {
+                    let dist = corner.dot(axis);
+                    (min.min(dist), max.max(dist))
+                })
+        };
+
+        let mut normal = Vec2f::zero();
+        let mut depth = f64::INFINITY;
+        for axis in axes {
+            let (min, max) = project(&corners, axis);
+            let (other_min, other_max) = project(&other_corners, axis);
+            let overlap = max.min(other_max) - min.max(other_min);
+            if overlap <= 0.0 {
+                return None;
+            }
+            if overlap < depth {
+                normal = axis;
+                depth = overlap;
+            }
+        }
+
+        // The hitbox center is not necessarily the vehicle's origin.
+        let center = corners.into_iter().sum::<Vec2f>() / 4.0;
+        let other_center = other_corners.into_iter().sum::<Vec2f>() / 4.0;
+        if normal.dot(other_center - center) < 0.0 {
+            normal = -normal;
+        }
+
+        Some((normal, depth))
+    }
+
+    /// Where the segment from `begin` to `end` first touches the hitbox, if at all.
+    ///
+    /// Returns `begin` if it's already inside.
+    pub fn segment_hit(self, pos: Vec2f, angle: f64, begin: Vec2f, end: Vec2f) -> Option<Vec2f> {
+        // Work in hitbox space where it's just an AABB.
+        let local_begin = (begin - pos).rotated_z(-angle);
+        let local_delta = (end - begin).rotated_z(-angle);
+
+        // Slab test - intersect the intervals where the segment is between mins and maxs on each axis.
+        let mut t_enter = 0.0_f64;
+        let mut t_exit = 1.0_f64;
+        for (begin, delta, min, max) in [
+            (local_begin.x, local_delta.x, self.mins.x, self.maxs.x),
+            (local_begin.y, local_delta.y, self.mins.y, self.maxs.y),
+        ] {
+            if delta == 0.0 {
+                if begin < min || begin > max {
+                    return None;
+                }
+            } else {
+                let t1 = (min - begin) / delta;
+                let t2 = (max - begin) / delta;
+                t_enter = t_enter.max(t1.min(t2));
+                t_exit = t_exit.min(t1.max(t2));
+            }
+        }
+
+        if t_enter <= t_exit {
+            Some(begin + (end - begin) * t_enter)
+        } else {
+            None
+        }
+    }
+
+    /// Distance from `point` to the nearest point of the hitbox, 0 if it's inside.
+    pub fn distance(self, pos: Vec2f, angle: f64, point: Vec2f) -> f64 {
+        let local = (point - pos).rotated_z(-angle);
+        let nearest = local.clamped(self.mins, self.maxs);
+        local.distance(nearest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hitbox_collision() {
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-20.0, -10.0),
+            maxs: Vec2f::new(20.0, 10.0),
+        };
+
+        // Side by side, overlapping by 5 along x.
+        let (normal, depth) = hitbox
+            .collision(Vec2f::zero(), 0.0, hitbox, Vec2f::new(35.0, 0.0), 0.0)
+            .unwrap();
+        assert!((normal - Vec2f::new(1.0, 0.0)).magnitude() < 0.0001);
+        assert!((depth - 5.0).abs() < 0.0001);
+
+        // The normal points towards the other hitbox.
+        let (normal, _) = hitbox
+            .collision(Vec2f::zero(), 0.0, hitbox, Vec2f::new(0.0, -15.0), 0.0)
+            .unwrap();
+        assert!((normal - Vec2f::new(0.0, -1.0)).magnitude() < 0.0001);
+
+        // Bounding circles would overlap but the rotated boxes don't.
+        let res = hitbox.collision(Vec2f::zero(), 0.0, hitbox, Vec2f::new(30.0, 22.0), PI / 2.0);
+        assert!(res.is_none());
+
+        // Rotated by 90 degrees, the other box reaches 10 to the left of its center.
+        let res = hitbox.collision(Vec2f::zero(), 0.0, hitbox, Vec2f::new(29.0, 0.0), PI / 2.0);
+        assert!(res.is_some());
+        let res = hitbox.collision(Vec2f::zero(), 0.0, hitbox, Vec2f::new(31.0, 0.0), PI / 2.0);
+        assert!(res.is_none());
+    }
+
+    #[test]
+    fn test_hitbox_segment_and_distance() {
+        let hitbox = Hitbox {
+            mins: Vec2f::new(-20.0, -5.0),
+            maxs: Vec2f::new(20.0, 5.0),
+        };
+        let pos = Vec2f::new(100.0, 100.0);
+
+        // A long thin vehicle is missed by a shot that would hit a circle.
+        let begin = Vec2f::new(98.0, 50.0);
+        let end = Vec2f::new(98.0, 90.0);
+        assert!(hitbox.segment_hit(pos, 0.0, begin, end).is_none());
+        // Unless it's rotated towards the shot.
+        let hit = hitbox.segment_hit(pos, PI / 2.0, begin, end).unwrap();
+        assert!((hit - Vec2f::new(98.0, 80.0)).magnitude() < 0.0001);
+
+        let hit = hitbox
+            .segment_hit(pos, 0.0, Vec2f::new(50.0, 100.0), Vec2f::new(150.0, 100.0))
+            .unwrap();
+        assert!((hit - Vec2f::new(80.0, 100.0)).magnitude() < 0.0001);
+        // Starting inside
+        assert_eq!(hitbox.segment_hit(pos, 0.0, pos, pos), Some(pos));
+
+        assert_eq!(hitbox.distance(pos, 0.0, pos), 0.0);
+        assert!((hitbox.distance(pos, 0.0, Vec2f::new(100.0, 115.0)) - 10.0).abs() < 0.0001);
+        assert!((hitbox.distance(pos, PI / 2.0, Vec2f::new(100.0, 125.0)) - 5.0).abs() < 0.0001);
+    }
 }
@@ -22,24 +22,18 @@ pub struct GameState {
     /// Game time after which the match ends
     pub time_limit: f64,
     pub game_mode: GameMode,
+    pub match_state: MatchState,
+    /// Indexed by `Team`.
+    pub team_scores: [TeamScore; 2],
 
     pub ais: Arena<Ai>,
     pub players: Arena<Player>,
     pub vehicles: Arena<Vehicle>,
     pub projectiles: Arena<Projectile>,
+    pub mines: Arena<Mine>,
 
-    /// Map of projectile handles to vehicle handles.
-    /// Prevents rail hitting the same vehicle twice
-    /// when one segment ends inside the hitbox and the next starts inside it the next frame.
-    /// This can for now only happen with railguns since all other projectiles get removed on hit.
-    /// LATER This is still not perfect since one segment can hit multiple vehicles in any order
-    /// and there's no guarantee the last vehicle is the one where the beam ends.
-    /// LATER This is a can of worms:
-    ///     1) Make sure (add test) one beam can kill the player and hit him again if he's unlucky enough to respawn in its path.
-    ///     2) Remove the entry after the projectile exits the hitbox - e.g. guided missiles that can pass through several times.
-    ///     3) Make sure the HashMap doesn't grow indefinitely in case we forgot to remove in some cases.
-    ///     4) Why is this even a hashmap? Keep this as SmallVec/Set on projectile?
-    pub rail_hits: FnvHashMap<Index, Index>,
+    /// Only in Capture The Cow.
+    pub cow: Option<Cow>,
 }
 
 impl GameState {
@@ -53,13 +47,16 @@ impl GameState {
 
             time_limit: 10.0 * 60.0,
             game_mode: GameMode::Ffa(Ffa { kill_limit: 20 }),
+            match_state: MatchState::Playing,
+            team_scores: Default::default(),
 
             ais: Arena::new(),
             players: Arena::new(),
             vehicles: Arena::new(),
             projectiles: Arena::new(),
+            mines: Arena::new(),
 
-            rail_hits: FnvHashMap::default(),
+            cow: None,
         }
     }
 }
@@ -77,6 +74,15 @@ pub enum GameMode {
     Ctc(Ctc),
 }
 
+impl GameMode {
+    pub fn has_teams(&self) -> bool {
+        match self {
+            GameMode::Ffa(_) => false,
+            GameMode::Tw(_) | GameMode::Ctc(_) => true,
+        }
+    }
+}
+
 /// Free For All
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Ffa {
@@ -95,6 +101,16 @@ pub struct Ctc {
     pub capture_limit: i32,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum MatchState {
+    Playing,
+    /// The match is over, gameplay is frozen and everyone sees the final scoreboard.
+    /// The next match starts at `end_time`.
+    Intermission {
+        end_time: f64,
+    },
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct RailBeam {
     pub begin: Vec2f,
@@ -28,6 +28,8 @@ pub struct ClientInput {
     pub horn: bool,
     pub chat: bool,
     pub pause: bool,
+    /// Switch between playing and observing.
+    pub observe: bool,
     // ^ when adding fields, also add them to Debug
 }
 
@@ -35,7 +37,7 @@ pub struct ClientInput {
 ///
 /// LATER Include stuff like timestamps.
 /// LATER Maybe treat some keys presses as events?
-#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NetInput {
     pub left: bool,
     pub right: bool,
@@ -99,6 +101,7 @@ impl ClientInput {
             horn: self.horn | other.horn,
             chat: self.chat | other.chat,
             pause: self.pause | other.pause,
+            observe: self.observe | other.observe,
         }
     }
 
@@ -169,7 +172,7 @@ impl NetInput {
 impl Debug for ClientInput {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         static_assert!(
-            std::mem::size_of::<ClientInput>() == 14,
+            std::mem::size_of::<ClientInput>() == 15,
             "number of fields changed without changing Debug impl"
         );
 
@@ -216,6 +219,9 @@ impl Debug for ClientInput {
         if self.pause {
             write!(f, "pause ")?;
         }
+        if self.observe {
+            write!(f, "observe ")?;
+        }
         write!(f, "}}")?;
         Ok(())
     }
@@ -342,6 +348,9 @@ pub fn get_input1() -> ClientInput {
     if was_input_pressed(&[KeyCode::Pause, KeyCode::P]) {
         input.pause = true;
     }
+    if was_input_pressed(&[KeyCode::O]) {
+        input.observe = true;
+    }
 
     input
 }
@@ -389,12 +398,24 @@ pub fn get_input2() -> ClientInput {
         input.horn = true;
     }
 
-    // No binds for shared actions like chat, pause, console and esc.
+    // No binds for shared actions like chat, pause, observe, console and esc.
     // They're defined on player 1.
 
     input
 }
 
+/// Characters typed since the last call, oldest first.
+///
+/// Macroquad's queue returns the newest first and is only emptied by reading it.
+pub fn get_chars_pressed() -> Vec<char> {
+    let mut chars = Vec::new();
+    while let Some(c) = get_char_pressed() {
+        chars.push(c);
+    }
+    chars.reverse();
+    chars
+}
+
 fn was_input_pressed(key_codes: &[KeyCode]) -> bool {
     for &key_code in key_codes {
         // Check both to avoid skipping input if it's pressed and released within one frame.
@@ -0,0 +1,235 @@
+//! Smooth movement of entities controlled by the server.
+//!
+//! Updates arrive at irregular intervals so applying them immediately causes judder.
+//! Instead the client buffers them and renders remote entities a short delay behind the server,
+//! interpolating between the two snapshots around that time.
+//! When updates are late, entities are extrapolated using their velocity for a little while.
+//!
+//! Our own vehicle is predicted instead (see `sys_prediction`).
+
+use crate::prelude::*;
+
+/// State of server-controlled entities at one server frame.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub game_time: f64,
+    pub vehicles: FnvHashMap<Index, EntityPhysics>,
+    pub projectiles: FnvHashMap<Index, EntityPhysics>,
+}
+
+impl ClientFrameCtx<'_> {
+    /// Remember the entities' state from an `Update` to interpolate between later.
+    pub fn add_snapshot(&mut self, game_time: f64, game_time_prev: f64, dt: f64) {
+        let mut resync = false;
+        if let Some(newest) = self.cg.snapshots.back() {
+            if game_time <= newest.game_time {
+                dbg_logf!("WARNING: outdated update gt: {game_time:.03}, ignoring");
+                return;
+            }
+            // The server sends an update every frame, a gap means we missed some.
+            // Short gaps are fine, we just interpolate over a longer interval.
+            // After long ones, the old snapshots and the server time estimate are stale.
+            let gap = game_time_prev - newest.game_time;
+            if gap > dt / 2.0 {
+                if self.cvars.d_log_updates_cl {
+                    dbg_logf!(
+                        "missed update(s) between gt: {:.03} and {:.03}",
+                        newest.game_time,
+                        game_time_prev
+                    );
+                }
+                if gap >= self.cvars.cl_server_time_resync {
+                    self.cg.snapshots.clear();
+                    resync = true;
+                }
+            }
+        }
+
+        let vehicles = self
+            .gs
+            .vehicles
+            .iter()
+            .map(|(handle, vehicle)| {
+                let physics = EntityPhysics {
+                    pos: vehicle.pos,
+                    vel: vehicle.vel,
+                    angle: vehicle.angle,
+                    turn_rate: vehicle.turn_rate,
+                };
+                (handle, physics)
+            })
+            .collect();
+        let projectiles = self
+            .gs
+            .projectiles
+            .iter()
+            .map(|(handle, projectile)| {
+                let physics = EntityPhysics {
+                    pos: projectile.pos,
+                    vel: projectile.vel,
+                    angle: projectile.angle,
+                    turn_rate: projectile.turn_rate,
+                };
+                (handle, physics)
+            })
+            .collect();
+        self.cg.snapshots.push_back(Snapshot {
+            game_time,
+            vehicles,
+            projectiles,
+        });
+
+        // Estimate the server time so we know how far behind it we're rendering.
+        // Updates arrive with varying delay so the estimate follows them only slowly
+        // unless it's way off (e.g. when connecting or after a lag spike).
+        let offset = game_time - self.gs.game_time;
+        self.cg.server_time_offset = match self.cg.server_time_offset {
+            Some(prev) if !resync && (offset - prev).abs() < self.cvars.cl_server_time_resync => {
+                Some(prev + (offset - prev) * self.cvars.cl_server_time_smoothing)
+            }
+            _ => Some(offset),
+        };
+
+        // Also here, not just in `sys_interpolation`, so they don't pile up while paused.
+        self.prune_snapshots();
+    }
+
+    /// Move remote vehicles and projectiles to where they were `cl_interpolation_delay` ago.
+    pub fn sys_interpolation(&mut self) {
+        let Some(time) = self.prune_snapshots() else {
+            return;
+        };
+
+        if !self.cvars.cl_interpolation {
+            return;
+        }
+
+        let predicted_vehicle = if self.cvars.cl_prediction {
+            self.gs.players[self.cg.tmp_local_player_handle].vehicle
+        } else {
+            None
+        };
+        let extrapolation_max = self.cvars.cl_extrapolation_max;
+
+        for (handle, vehicle) in self.gs.vehicles.iter_mut() {
+            if Some(handle) == predicted_vehicle {
+                continue;
+            }
+            let sample = sample(&self.cg.snapshots, time, extrapolation_max, |snapshot| {
+                snapshot.vehicles.get(&handle)
+            });
+            if let Some((pos, angle)) = sample {
+                vehicle.pos = pos;
+                vehicle.angle = angle;
+            }
+        }
+
+        for (handle, projectile) in self.gs.projectiles.iter_mut() {
+            let sample = sample(&self.cg.snapshots, time, extrapolation_max, |snapshot| {
+                snapshot.projectiles.get(&handle)
+            });
+            if let Some((pos, angle)) = sample {
+                projectile.pos = pos;
+                projectile.angle = angle;
+            }
+        }
+    }
+
+    /// Keep only one snapshot older than the time we render.
+    ///
+    /// Returns the render time or None if we don't know the server time yet.
+    fn prune_snapshots(&mut self) -> Option<f64> {
+        let offset = self.cg.server_time_offset?;
+        let time = self.gs.game_time + offset - self.cvars.cl_interpolation_delay;
+        while self.cg.snapshots.len() >= 2 && self.cg.snapshots[1].game_time <= time {
+            self.cg.snapshots.pop_front();
+        }
+        Some(time)
+    }
+}
+
+/// Position and angle of one entity at the given time.
+///
+/// Interpolates between the snapshots around `time`,
+/// extrapolates by at most `extrapolation_max` seconds after the last one.
+/// Before the entity's first snapshot, returns its first known state.
+fn sample<'a, F>(
+    snapshots: &'a VecDeque<Snapshot>,
+    time: f64,
+    extrapolation_max: f64,
+    get: F,
+) -> Option<(Vec2f, f64)>
+where
+    F: Fn(&'a Snapshot) -> Option<&'a EntityPhysics>,
+{
+    let mut prev: Option<(f64, &EntityPhysics)> = None;
+    for snapshot in snapshots {
+        let Some(physics) = get(snapshot) else {
+            continue;
+        };
+        if snapshot.game_time >= time {
+            let Some((prev_time, prev_physics)) = prev else {
+                return Some((physics.pos, physics.angle));
+            };
+            let t = (time - prev_time) / (snapshot.game_time - prev_time);
+            let pos = prev_physics.pos + (physics.pos - prev_physics.pos) * t;
+            let angle = prev_physics.angle + prev_physics.angle.delta_angle(physics.angle) * t;
+            return Some((pos, angle.rem_euclid(2.0 * PI)));
+        }
+        prev = Some((snapshot.game_time, physics));
+    }
+
+    let (prev_time, prev_physics) = prev?;
+    let ahead = (time - prev_time).min(extrapolation_max);
+    Some((
+        prev_physics.pos + prev_physics.vel * ahead,
+        prev_physics.angle,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(game_time: f64, handle: Index, pos: Vec2f, angle: f64) -> Snapshot {
+        let physics = EntityPhysics {
+            pos,
+            vel: v!(100 0),
+            angle,
+            turn_rate: 0.0,
+        };
+        let mut vehicles = FnvHashMap::default();
+        vehicles.insert(handle, physics);
+        Snapshot {
+            game_time,
+            vehicles,
+            projectiles: FnvHashMap::default(),
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let handle = Index::from_bits(1 << 32).unwrap();
+        let snapshots = VecDeque::from([
+            snapshot(1.0, handle, v!(0 0), 350.0_f64.to_radians()),
+            snapshot(2.0, handle, v!(10 20), 10.0_f64.to_radians()),
+        ]);
+        let sample = |time| sample(&snapshots, time, 0.5, |s| s.vehicles.get(&handle)).unwrap();
+
+        assert_eq!(sample(0.5), (v!(0 0), 350.0_f64.to_radians()));
+
+        // Angles interpolate the short way across 0.
+        let (pos, angle) = sample(1.5);
+        assert_eq!(pos, v!(5 10));
+        assert!(angle.abs() < 1e-9 || (angle - 2.0 * PI).abs() < 1e-9);
+
+        assert_eq!(sample(2.25).0, v!(35 20));
+        assert_eq!(sample(10.0).0, v!(60 20));
+
+        let other = Index::from_bits((1 << 32) + 1).unwrap();
+        assert_eq!(
+            super::sample(&snapshots, 1.5, 0.5, |s| s.vehicles.get(&other)),
+            None
+        );
+    }
+}
@@ -17,26 +17,34 @@ pub mod client;
 pub mod common;
 pub mod context;
 pub mod cvars;
+pub mod delta;
 pub mod entities;
 pub mod game_state;
 pub mod input;
+pub mod interpolation;
 pub mod map;
+pub mod nav;
 pub mod net;
 pub mod net_messages;
 pub mod prelude;
 pub mod rendering;
 pub mod server;
+pub mod sim;
+pub mod stats;
 pub mod sys_ai;
 pub mod systems;
 pub mod timing;
 pub mod utils;
 pub mod weapons;
 
-use std::{env, error::Error, panic, process::Command};
+use std::{env, error::Error, io, net::TcpListener, panic, process::Command};
 
 use macroquad::prelude::*;
 
-use crate::{net::Connection, prelude::*};
+use crate::{
+    net::{udp::UdpListener, websocket::WsListener, Connection, Listener},
+    prelude::*,
+};
 
 const BOT_NAMES: [&str; 20] = [
     "Dr. Dead",
@@ -69,21 +77,19 @@ enum Endpoint {
     Client,
     /// Run only the game server
     Server,
+    /// Run bot matches without a window as fast as possible and save stats
+    Sim,
 }
 
-fn window_conf() -> Conf {
-    // This is a hack because macroquad doesn't really allow modifying the window later.
-    // LATER Find out what #[macroquad] expands to, do it manually
-    //  but only if on client so server doesn't depend on macroquad.
-    let arg = env::args().skip(1).next();
-    let title = match arg.as_deref() {
-        Some("server") => "RecWars Server",
-        Some("client") => "RecWars Client",
-        Some("local") => "RecWars Local",
-        _ => "RecWars Launcher",
+fn window_conf(endpoint: &Option<Endpoint>) -> Conf {
+    let title = match endpoint {
+        Some(Endpoint::Server) => "RecWars Server",
+        Some(Endpoint::Client) => "RecWars Client",
+        Some(Endpoint::Local) => "RecWars Local",
+        Some(Endpoint::Sim) | None => "RecWars Launcher",
     };
-    let (width, height) = match arg.as_deref() {
-        Some("client" | "local") => (1600, 900),
+    let (width, height) = match endpoint {
+        Some(Endpoint::Client | Endpoint::Local) => (1600, 900),
         _ => (400, 200),
     };
     Conf {
@@ -100,8 +106,8 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() -> Result<(), Box<dyn Error>> {
+// Not using #[macroquad::main] so we can decide whether to open a window at all.
+fn main() -> Result<(), Box<dyn Error>> {
     // We are not using a derive-based library (anymore)
     // because they add a couple hundred ms to incremental debug builds.
     //
@@ -126,15 +132,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
             args.next();
             Some(Endpoint::Server)
         }
+        Some("sim") => {
+            args.next();
+            Some(Endpoint::Sim)
+        }
         #[rustfmt::skip]
         Some("--help") => {
-            println!("Usage: rec-wars [launcher|local|client|server] [cvar1 value1 cvar2 value2 ...]");
+            println!("Usage: rec-wars [launcher|local|client|server|sim] [cvar1 value1 cvar2 value2 ...]");
             println!();
             println!("Commands (optional):");
             println!("    launcher   Run a local game with separate client and server processes (default)");
             println!("    local      Run a local game with client and server in one process (experimental)");
             println!("    client     Run only the game client");
             println!("    server     Run only the dedicated game server");
+            println!("    sim        Run bot matches without a window and save stats (see sim_* cvars)");
             println!();
             println!("Cvars (optional):");
             println!("    You can specify cvars in key value pairs separated by space.");
@@ -166,32 +177,45 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // We might wanna require that too but this is slightly less typing for now.
     let cvar_args = args.collect();
 
-    // Force local mode in WASM for now.
+    // There are no command line arguments in WASM
+    // and browsers can't run a server so connect to one.
     #[cfg(target_arch = "wasm32")]
     {
-        endpoint = Some(Endpoint::Local);
+        endpoint = Some(Endpoint::Client);
     }
 
+    let conf = window_conf(&endpoint);
     match endpoint {
         // LATER None should launch client and offer choice in menu
         None => {
             init_global_state("launcher");
-            client_server_main(cvar_args).await;
+            macroquad::Window::from_config(conf, client_server_main(cvar_args));
         }
         Some(Endpoint::Local) => {
             init_global_state("lo");
             let cvars = args_to_cvars(&cvar_args)?;
-            client_main(cvars, true).await;
+            macroquad::Window::from_config(conf, client_main(cvars, true));
         }
         Some(Endpoint::Client) => {
             init_global_state("cl");
             let cvars = args_to_cvars(&cvar_args)?;
-            client_main(cvars, false).await;
+            macroquad::Window::from_config(conf, client_main(cvars, false));
         }
         Some(Endpoint::Server) => {
             init_global_state("sv");
             let cvars = args_to_cvars(&cvar_args)?;
-            server_main(cvars).await;
+            // Bind before opening the window so a busy port is reported as an error.
+            let listeners = bind_listeners(&cvars)?;
+            let controller_listener = bind_controller_listener(&cvars)?;
+            macroquad::Window::from_config(
+                conf,
+                server_main(cvars, listeners, controller_listener),
+            );
+        }
+        Some(Endpoint::Sim) => {
+            init_global_state("sim");
+            let cvars = args_to_cvars(&cvar_args)?;
+            sim::sim_main(cvars)?;
         }
     }
 
@@ -241,6 +265,12 @@ fn args_to_cvars(cvar_args: &[String]) -> Result<Cvars, String> {
         }
     }
 
+    // Browsers can only use WebSockets.
+    #[cfg(target_arch = "wasm32")]
+    {
+        cvars.cl_net_transport = Transport::WebSocket;
+    }
+
     // Hack for web until there's a menu
     #[cfg(feature = "web_splitscreen")]
     {
@@ -306,8 +336,12 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     //  Option 1: state machine: menu, connecting, playing - one main loop for all.
     //  Option 2: separate "main" loops, after game loop ends, return back into menu loop.
 
+    let addr = match cvars.cl_net_transport {
+        Transport::Tcp | Transport::Udp => &cvars.cl_net_server_addr,
+        Transport::WebSocket => &cvars.cl_net_websocket_url,
+    };
     draw_text(
-        &format!("Connecting to {}...", &cvars.cl_net_server_addr),
+        &format!("Connecting to {}...", addr),
         200.0,
         200.0,
         32.0,
@@ -315,14 +349,57 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     );
     next_frame().await;
 
-    let mut conn: Box<dyn Connection<ServerMessage>> =
-        Box::new(net::tcp_connect_blocking(&cvars, &cvars.cl_net_server_addr));
+    let mut conn: Box<dyn Connection<ServerMessage>> = match cvars.cl_net_transport {
+        Transport::Tcp => Box::new(net::tcp_connect_blocking(&cvars, addr)),
+        Transport::Udp => Box::new(net::udp::udp_connect_blocking(&cvars, addr)),
+        #[cfg(not(target_arch = "wasm32"))]
+        Transport::WebSocket => Box::new(net::websocket::ws_connect(&cvars, addr)),
+        #[cfg(target_arch = "wasm32")]
+        Transport::WebSocket => Box::new(net::websocket::browser::ws_connect(&cvars, addr)),
+    };
+
+    // The version has to be the first message so the server can reject us
+    // before sending any data we might not understand.
+    let cl_version = Version::current();
+    let msg = ClientMessage::Version(cl_version.clone());
+    let net_msg = net::serialize(msg);
+    let res = conn.send(&net_msg);
+    res.unwrap(); // LATER
+
+    let sv_version = loop {
+        let (msg, closed) = conn.receive_one();
+
+        // The server closes the connection right after rejecting us
+        // so check the message first.
+        match msg {
+            Some(ServerMessage::Version(sv_version)) => break sv_version,
+            Some(ServerMessage::Rejected(reason)) => {
+                show_rejection(&reason).await;
+                return;
+            }
+            Some(msg) => dbg_logf!("WARNING: Unexpected message type: {:?}", msg),
+            None if closed => {
+                show_rejection("The server closed the connection during the handshake").await;
+                return;
+            }
+            None => {}
+        }
+
+        next_frame().await;
+    };
+    dbg_logf!("Server version: {} {:?}", sv_version, sv_version);
+    dbg_logf!("Client version: {} {:?}", cl_version, cl_version);
+    // The server already checked but it might be older and more lenient.
+    if let Err(reason) = Version::check_compatible(&cl_version, &sv_version) {
+        show_rejection(&reason).await;
+        return;
+    }
 
     // LATER(splitscreen) handle 2 networked players on 1 connection (need to tell server how many players to spawn)
     let connect = Connect {
-        cl_version: env!("GIT_VERSION").to_owned(),
         name1: cvars.cl_name1.clone(),
         name2: None,
+        vehicle: cvars.cl_vehicle,
     };
     let msg = ClientMessage::Connect(connect);
     let net_msg = net::serialize(msg);
@@ -352,35 +429,35 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     // Using destructuring here so we get an error if a field is added but not read.
 
     let Init {
-        sv_version,
         map_path,
         frame_num,
         game_time,
         game_time_prev,
         dt,
+        time_limit,
+        game_mode,
+        match_state,
+        team_scores,
         players,
         local_player1_index,
         local_player2_index,
         vehicles,
         projectiles,
+        mines,
+        cow,
     } = init;
     assert!(local_player2_index.is_none()); // LATER
 
-    let cl_version = env!("GIT_VERSION");
-    dbg_logf!("Server version: {}", sv_version);
-    dbg_logf!("Client version: {}", cl_version);
-    if sv_version == cl_version {
-        dbg_logf!("Versions match");
-    } else {
-        dbg_logf!("WARNING: Client and server versions don't match");
-    }
-
     let map = load_map(&assets, &map_path);
     let mut gs = GameState::new();
     gs.frame_num = frame_num;
     gs.game_time = game_time;
     gs.game_time_prev = game_time_prev;
     gs.dt = dt;
+    gs.time_limit = time_limit;
+    gs.game_mode = game_mode;
+    gs.match_state = match_state;
+    gs.team_scores = team_scores;
 
     let mut ctx = FrameCtx::new(&cvars, &map, &mut gs);
     for player in players {
@@ -392,6 +469,14 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     for projectile in projectiles {
         ctx.init_projectile(projectile);
     }
+    for mine in mines {
+        ctx.init_mine(mine);
+    }
+    ctx.gs.cow = cow.map(|CowInit { pos, carrier }| {
+        let mut cow = Cow::new(pos);
+        cow.carrier = carrier.map(|index| ctx.gs.players.slot_to_index(index).unwrap());
+        cow
+    });
 
     let player1_handle = gs.players.slot_to_index(local_player1_index).unwrap();
     // LATER After RustCycles has editor integration and has separate matches,
@@ -408,7 +493,10 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
 
         client.render(&cvars);
 
-        client.console.update(&mut cvars);
+        // Keys typed into chat shouldn't open the console.
+        if client.cg.chat_line.is_none() {
+            client.console.update(&mut cvars);
+        }
 
         client.post_render(&cvars);
 
@@ -420,13 +508,70 @@ async fn client_main(mut cvars: Cvars, local_game: bool) {
     }
 }
 
-async fn server_main(mut cvars: Cvars) {
+/// Tell the player why we can't connect, wait until they close it.
+async fn show_rejection(reason: &str) {
+    dbg_logf!("Can't connect to the server: {reason}");
+    loop {
+        clear_background(BLACK);
+        draw_text("Can't connect to the server:", 200.0, 200.0, 32.0, RED);
+        draw_text(reason, 200.0, 240.0, 32.0, RED);
+        draw_text("Press Escape to quit", 200.0, 300.0, 24.0, GRAY);
+        if is_key_pressed(KeyCode::Escape) {
+            return;
+        }
+        next_frame().await;
+    }
+}
+
+fn bind_listeners(cvars: &Cvars) -> io::Result<Vec<Box<dyn Listener<ClientMessage>>>> {
+    let mut listeners: Vec<Box<dyn Listener<ClientMessage>>> = Vec::new();
+    let listener = TcpListener::bind(&cvars.sv_net_listen_addr)?;
+    listener.set_nonblocking(true)?;
+    dbg_logf!("Listening on {} (TCP)", &cvars.sv_net_listen_addr);
+    listeners.push(Box::new(listener));
+    if !cvars.sv_net_udp_listen_addr.is_empty() {
+        let listener = UdpListener::bind(&cvars.sv_net_udp_listen_addr, cvars.sv_net_timeout)?;
+        dbg_logf!("Listening on {} (UDP)", listener.local_addr()?);
+        listeners.push(Box::new(listener));
+    }
+    if !cvars.sv_net_websocket_listen_addr.is_empty() {
+        let listener = WsListener::bind(&cvars.sv_net_websocket_listen_addr)?;
+        dbg_logf!("Listening on {} (WebSocket)", listener.local_addr()?);
+        listeners.push(Box::new(listener));
+    }
+    Ok(listeners)
+}
+
+fn bind_controller_listener(cvars: &Cvars) -> io::Result<Option<Box<dyn Listener<ClientMessage>>>> {
+    if cvars.sv_net_controller_listen_addr.is_empty() {
+        return Ok(None);
+    }
+    let addr = &cvars.sv_net_controller_listen_addr;
+    let listener = TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    dbg_logf!("Listening for bot controllers on {}", addr);
+    Ok(Some(Box::new(listener)))
+}
+
+async fn server_main(
+    mut cvars: Cvars,
+    listeners: Vec<Box<dyn Listener<ClientMessage>>>,
+    controller_listener: Option<Box<dyn Listener<ClientMessage>>>,
+) {
     init_seed(&mut cvars);
     let assets = Assets::load_all().await;
 
     let map_path = select_map(&mut cvars, &assets).to_owned();
     let map = load_map(&assets, &map_path);
-    let mut server = Server::new(&cvars, map);
+    let bot_profiles = sys_ai::parse_bot_profiles(&assets.bot_profiles);
+    let mut server = Server::new(
+        &cvars,
+        map,
+        bot_profiles,
+        listeners,
+        controller_listener,
+        get_time,
+    );
 
     loop {
         server.update(&cvars, get_time());
@@ -137,6 +137,17 @@ impl Map {
             return Some(begin);
         }
 
+        let delta = end - begin;
+        let nudge = delta.normalized() * 0.01;
+        self.trace_wall(begin, end).map(|hit| hit.pos + nudge)
+    }
+
+    /// Find first wall collision when traveling from `begin` to `end`
+    /// including the normal of the tile edge that was hit.
+    ///
+    /// `begin` should not be inside a wall, use `is_wall_trace` if that's possible.
+    /// Area outside the map is considered wall.
+    pub fn trace_wall(&self, begin: Vec2f, end: Vec2f) -> Option<WallHit> {
         // similar to the simple, unoptimized version here:
         // https://playtechs.blogspot.com/2007/03/raytracing-on-grid.html
 
@@ -178,12 +189,15 @@ impl Map {
         let t_step_y = TILE_SIZE / delta.y.abs();
         loop {
             let t;
+            let normal;
             if t_x < t_y {
                 t = t_x;
                 t_x += t_step_x;
+                normal = Vec2f::new(-delta.x.signum(), 0.0);
             } else {
                 t = t_y;
                 t_y += t_step_y;
+                normal = Vec2f::new(0.0, -delta.y.signum());
             };
             if t > 1.0 {
                 return None;
@@ -191,22 +205,134 @@ impl Map {
             let intersection = begin + delta * t;
             let wall = intersection + nudge;
             if self.is_wall(wall) {
-                return Some(wall);
+                let maxs = self.maxs();
+                let map_edge =
+                    wall.x <= 0.0 || wall.y <= 0.0 || wall.x >= maxs.x || wall.y >= maxs.y;
+                return Some(WallHit {
+                    t,
+                    pos: intersection,
+                    normal,
+                    map_edge,
+                });
             }
         }
     }
 
+    /// Is any corner of the hitbox outside the map or inside a wall?
+    pub fn is_wall_hitbox(&self, hitbox: Hitbox, pos: Vec2f, angle: f64) -> bool {
+        hitbox
+            .corners(pos, angle)
+            .iter()
+            .any(|&corner| self.is_wall(corner))
+    }
+
+    /// Find a position near `pos` where the hitbox is not in a wall
+    /// by pushing the corners that are in walls out through the nearest free tile edge.
+    ///
+    /// Meant for small overlaps such as after turning next to a wall.
+    pub fn push_out_hitbox(&self, hitbox: Hitbox, pos: Vec2f, angle: f64) -> Option<Vec2f> {
+        let mut push = Vec2f::zero();
+        for corner in hitbox.corners(pos, angle) {
+            if !self.is_wall(corner) {
+                continue;
+            }
+
+            let tile_min = (corner / TILE_SIZE).floor() * TILE_SIZE;
+            let offset = corner - tile_min;
+            let candidates = [
+                Vec2f::new(-offset.x - 0.01, 0.0),
+                Vec2f::new(TILE_SIZE - offset.x + 0.01, 0.0),
+                Vec2f::new(0.0, -offset.y - 0.01),
+                Vec2f::new(0.0, TILE_SIZE - offset.y + 0.01),
+            ];
+            let corner_push = candidates
+                .into_iter()
+                .filter(|&candidate| !self.is_wall(corner + candidate))
+                .min_by(|a, b| a.magnitude_squared().total_cmp(&b.magnitude_squared()))?;
+
+            // Combine pushes of multiple corners - they can't go in opposite directions.
+            for (total, new) in [(&mut push.x, corner_push.x), (&mut push.y, corner_push.y)] {
+                if *total * new < 0.0 {
+                    return None;
+                }
+                if new.abs() > total.abs() {
+                    *total = new;
+                }
+            }
+        }
+
+        let new_pos = pos + push;
+        if self.is_wall_hitbox(hitbox, new_pos, angle) {
+            None
+        } else {
+            Some(new_pos)
+        }
+    }
+
     pub fn spawns(&self) -> &Vec<Vec2u> {
         &self.spawns
     }
 
     // LATER remove all #[allow(dead_code)] here (or the fns if they turn out useless)
 
-    #[allow(dead_code)]
     pub fn bases(&self) -> &Vec<Vec2u> {
         &self.bases
     }
 
+    /// Which team a base belongs to.
+    ///
+    /// Maps don't store this so the map is split in half along its longer side,
+    /// red gets the left/top half, blue the right/bottom.
+    /// Some maps (e.g. Damned Rockets) intentionally give one team more bases.
+    pub fn base_team(&self, base: Vec2u) -> Team {
+        let (coord, size) = if self.width() >= self.height() {
+            (base.x, self.width())
+        } else {
+            (base.y, self.height())
+        };
+        if coord * 2 < size {
+            Team::Red
+        } else {
+            Team::Blue
+        }
+    }
+
+    /// Where the cow starts and returns after a capture -
+    /// the center of the non-wall tile closest to halfway between the teams' bases.
+    ///
+    /// Returns None if either team has no bases.
+    pub fn cow_spawn(&self) -> Option<Vec2f> {
+        let mut sums = [Vec2f::zero(); 2];
+        let mut counts = [0.0; 2];
+        for &base in &self.bases {
+            let team = self.base_team(base) as usize;
+            sums[team] += self.tile_center(base);
+            counts[team] += 1.0;
+        }
+        if counts.contains(&0.0) {
+            return None;
+        }
+        let halfway = (sums[0] / counts[0] + sums[1] / counts[1]) / 2.0;
+
+        let mut best = None;
+        let mut best_dist2 = f64::INFINITY;
+        for c in 0..self.width() {
+            for r in 0..self.height() {
+                let index = Vec2u::new(c, r);
+                if self.surface_at_index(index).kind == SurfaceKind::Wall {
+                    continue;
+                }
+                let center = self.tile_center(index);
+                let dist2 = center.distance_squared(halfway);
+                if dist2 < best_dist2 {
+                    best = Some(center);
+                    best_dist2 = dist2;
+                }
+            }
+        }
+        best
+    }
+
     pub fn count_tiles(&self) -> usize {
         self.width() * self.height()
     }
@@ -225,20 +351,6 @@ impl Map {
         cnt
     }
 
-    /// Returns (pos, angle).
-    pub fn random_spawn(&self, rng: &mut Xoshiro256PlusPlus) -> (Vec2f, f64) {
-        if self.spawns().is_empty() {
-            dbg_logf!("WARNING: no spawns in map, using a random nonwall");
-            return self.random_nonwall(rng);
-        }
-
-        let i = rng.gen_range(0..self.spawns().len());
-        let index = self.spawns()[i];
-        let pos = self.tile_center(index);
-        let angle = self[index].angle;
-        (pos, angle)
-    }
-
     /// Returns (pos, angle).
     pub fn random_nonwall(&self, rng: &mut Xoshiro256PlusPlus) -> (Vec2f, f64) {
         loop {
@@ -261,6 +373,19 @@ impl Index<Vec2u> for Map {
     }
 }
 
+/// Where a trace first hit a wall.
+#[derive(Debug, Clone, Copy)]
+pub struct WallHit {
+    /// Fraction of the traced distance travelled before the hit.
+    pub t: f64,
+    /// Intersection with the edge of the wall tile.
+    pub pos: Vec2f,
+    /// Normal of the tile edge, pointing out of the wall.
+    pub normal: Vec2f,
+    /// The wall is the edge of the map, not a wall tile.
+    pub map_edge: bool,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct TilePos {
     /// Position of the tile in the map
@@ -281,9 +406,9 @@ pub struct Tile {
 pub struct Surface {
     pub name: String,
     pub kind: SurfaceKind,
-    /// Seems to affect both turning and acceleration
+    /// Seems to affect both turning and acceleration, see `MovementStats::on_surface`
     pub friction: f32,
-    /// Maybe a multiplier for speed
+    /// Maybe a multiplier for speed, see `MovementStats::on_surface`
     pub speed: f32,
 }
 
@@ -301,7 +426,7 @@ impl Surface {
 /// Special behavior of some surfaces.
 ///
 /// Reverse engineered by modifying RecWar's TextureList.txt and seeing what happens.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRepr)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromRepr, Deserialize, Serialize)]
 pub enum SurfaceKind {
     /// No special behavior beyond the normal surface properties
     Normal = 0,
@@ -309,9 +434,11 @@ pub enum SurfaceKind {
     Spawn = 1,
     /// Solid - can't move through it, most weapons can't shoot through it
     Wall = 2,
-    /// Vehicles on it spawn particles on their sides
+    /// Vehicles on it spawn particles on their sides.
+    /// Hovercraft ignore its friction and speed.
     Water = 3,
-    /// I don't see any effect
+    /// I don't see any effect in RecWar.
+    /// Here vehicles turn worse on it.
     Snow = 4,
     /// Base for Capture the Cow
     Base = 5,
@@ -449,5 +576,40 @@ mod tests {
         let up = Vec2f::new(0.0, -10.0);
         assert!(map.is_wall_trace(bottom_left, top_right + up).is_none());
         assert!(map.is_wall_trace(bottom_left, top_right - up).is_some());
+
+        let hit = map.trace_wall(top_left, outside).unwrap();
+        assert!(hit.map_edge);
+        assert_eq!(hit.normal, Vec2f::new(0.0, 1.0));
+        let hit = map.trace_wall(bottom_left, top_right - up).unwrap();
+        assert!(!hit.map_edge);
+        assert!(hit.t > 0.0 && hit.t < 1.0);
+        assert!(map.trace_wall(bottom_left, top_right + up).is_none());
+    }
+
+    #[test]
+    fn test_bases_and_cow() {
+        let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
+        let surfaces = parse_texture_list(&tex_list_text);
+
+        let map_text = fs::read_to_string("maps/extra/Damned Rockets (2).map").unwrap();
+        let map = parse_map(&map_text, surfaces.clone(), "");
+        let teams: Vec<_> = map.bases().iter().map(|&b| map.base_team(b)).collect();
+        assert_eq!(teams, [Team::Blue, Team::Red, Team::Blue]);
+        let cow = map.cow_spawn().unwrap();
+        assert!(!map.is_wall(cow));
+
+        // Tall map - split along Y
+        let map_text = fs::read_to_string("maps/extra/A Cow Too Far.map").unwrap();
+        let map = parse_map(&map_text, surfaces.clone(), "");
+        let teams: Vec<_> = map.bases().iter().map(|&b| map.base_team(b)).collect();
+        assert_eq!(teams, [Team::Red, Team::Blue]);
+        let cow = map.cow_spawn().unwrap();
+        assert!(!map.is_wall(cow));
+        assert_eq!(map.tile_pos(cow).index.y, 49);
+
+        let map_text = fs::read_to_string("maps/Arena.map").unwrap();
+        let map = parse_map(&map_text, surfaces, "");
+        assert!(map.bases().is_empty());
+        assert!(map.cow_spawn().is_none());
     }
 }
@@ -0,0 +1,271 @@
+//! Navigation for bots - a graph of walkable tiles and A* pathfinding on it.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::{
+    map::{SurfaceKind, TILE_SIZE},
+    prelude::*,
+};
+
+/// Which tiles vehicles can drive on.
+///
+/// Each non-wall tile is a node, edges go to the 8 neighbors.
+/// Diagonal edges only exist if both adjacent orthogonal tiles are walkable
+/// so paths don't cut corners of walls where vehicles would get stuck.
+#[derive(Debug, Clone)]
+pub struct NavGraph {
+    size: Vec2u,
+    /// Row-major like `Map`'s tiles.
+    walkable: Vec<bool>,
+}
+
+impl NavGraph {
+    pub fn new(map: &Map) -> Self {
+        let size = map.size();
+        let mut walkable = Vec::with_capacity(size.x * size.y);
+        for r in 0..size.y {
+            for c in 0..size.x {
+                let index = Vec2u::new(c, r);
+                walkable.push(map.surface_at_index(index).kind != SurfaceKind::Wall);
+            }
+        }
+        Self { size, walkable }
+    }
+
+    pub fn is_walkable(&self, index: Vec2u) -> bool {
+        index.x < self.size.x && index.y < self.size.y && self.walkable[self.flat(index)]
+    }
+
+    /// The nearest walkable tile to the world position.
+    ///
+    /// Vehicles can have their center above a wall tile when they're touching it,
+    /// this finds a tile they can actually path from.
+    pub fn nearest_walkable(&self, map: &Map, pos: Vec2f) -> Option<Vec2u> {
+        let index = map.tile_pos(pos).index;
+        if self.is_walkable(index) {
+            return Some(index);
+        }
+
+        self.neighbors(index, true)
+            .map(|(neighbor, _)| neighbor)
+            .filter(|&neighbor| self.is_walkable(neighbor))
+            .min_by(|&a, &b| {
+                let dist_a = map.tile_center(a).distance_squared(pos);
+                let dist_b = map.tile_center(b).distance_squared(pos);
+                dist_a.total_cmp(&dist_b)
+            })
+    }
+
+    /// Find the shortest path using A*.
+    ///
+    /// The returned path includes both `start` and `goal`.
+    pub fn find_path(&self, start: Vec2u, goal: Vec2u) -> Option<Vec<Vec2u>> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+
+        let mut costs = vec![f64::INFINITY; self.walkable.len()];
+        let mut came_from = vec![usize::MAX; self.walkable.len()];
+        let mut open = BinaryHeap::new();
+
+        costs[self.flat(start)] = 0.0;
+        open.push(Node {
+            index: start,
+            estimate: self.heuristic(start, goal),
+        });
+
+        while let Some(Node { index, .. }) = open.pop() {
+            if index == goal {
+                return Some(self.reconstruct(&came_from, goal));
+            }
+
+            let cost = costs[self.flat(index)];
+            for (neighbor, step) in self.neighbors(index, false) {
+                let new_cost = cost + step;
+                let flat = self.flat(neighbor);
+                if new_cost < costs[flat] {
+                    costs[flat] = new_cost;
+                    came_from[flat] = self.flat(index);
+                    open.push(Node {
+                        index: neighbor,
+                        estimate: new_cost + self.heuristic(neighbor, goal),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns (neighbor, distance) pairs, distance is in tiles.
+    ///
+    /// With `any`, returns all neighbors in the map, otherwise only those you can move to.
+    fn neighbors(&self, index: Vec2u, any: bool) -> impl Iterator<Item = (Vec2u, f64)> + '_ {
+        const DIRS: [(isize, isize); 8] = [
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+        ];
+
+        let offset = move |dx: isize, dy: isize| {
+            let x = index.x.checked_add_signed(dx)?;
+            let y = index.y.checked_add_signed(dy)?;
+            if x < self.size.x && y < self.size.y {
+                Some(Vec2u::new(x, y))
+            } else {
+                None
+            }
+        };
+
+        DIRS.into_iter().filter_map(move |(dx, dy)| {
+            let neighbor = offset(dx, dy)?;
+            if any {
+                return Some((neighbor, 1.0));
+            }
+            if !self.is_walkable(neighbor) {
+                return None;
+            }
+            if dx != 0 && dy != 0 {
+                let side1 = offset(dx, 0)?;
+                let side2 = offset(0, dy)?;
+                if !self.is_walkable(side1) || !self.is_walkable(side2) {
+                    return None;
+                }
+                Some((neighbor, std::f64::consts::SQRT_2))
+            } else {
+                Some((neighbor, 1.0))
+            }
+        })
+    }
+
+    /// Octile distance - exact when there are no walls.
+    fn heuristic(&self, a: Vec2u, b: Vec2u) -> f64 {
+        let dx = a.x.abs_diff(b.x) as f64;
+        let dy = a.y.abs_diff(b.y) as f64;
+        dx.max(dy) + (std::f64::consts::SQRT_2 - 1.0) * dx.min(dy)
+    }
+
+    fn reconstruct(&self, came_from: &[usize], goal: Vec2u) -> Vec<Vec2u> {
+        let mut path = vec![goal];
+        let mut flat = self.flat(goal);
+        while came_from[flat] != usize::MAX {
+            flat = came_from[flat];
+            path.push(Vec2u::new(flat % self.size.x, flat / self.size.x));
+        }
+        path.reverse();
+        path
+    }
+
+    fn flat(&self, index: Vec2u) -> usize {
+        index.y * self.size.x + index.x
+    }
+}
+
+/// Length of a path in world units, going through tile centers.
+pub fn path_length(path: &[Vec2u]) -> f64 {
+    path.windows(2)
+        .map(|pair| (pair[0].as_::<f64>() - pair[1].as_::<f64>()).magnitude() * TILE_SIZE)
+        .sum()
+}
+
+/// Entry in the A* open set, ordered so `BinaryHeap` pops the lowest estimate first.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    index: Vec2u,
+    estimate: f64,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::map::{parse_map, parse_texture_list};
+
+    use super::*;
+
+    fn graph(rows: &[&str]) -> NavGraph {
+        let size = Vec2u::new(rows[0].len(), rows.len());
+        let walkable = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|c| c == '.'))
+            .collect();
+        NavGraph { size, walkable }
+    }
+
+    #[test]
+    fn test_find_path() {
+        let nav = graph(&[
+            ".....", //
+            ".###.", //
+            "...#.", //
+            "##.#.", //
+            "...#.", //
+        ]);
+
+        // Around the wall, no cutting corners.
+        let path = nav.find_path(Vec2u::new(0, 4), Vec2u::new(4, 4)).unwrap();
+        assert_eq!(path.first(), Some(&Vec2u::new(0, 4)));
+        assert_eq!(path.last(), Some(&Vec2u::new(4, 4)));
+        for pair in path.windows(2) {
+            assert!(nav.is_walkable(pair[1]));
+            assert!(pair[0].x.abs_diff(pair[1].x) <= 1);
+            assert!(pair[0].y.abs_diff(pair[1].y) <= 1);
+        }
+        // Every diagonal shortcut cuts a wall corner so the path is only orthogonal steps.
+        assert_eq!(path.len(), 17);
+        assert!((path_length(&path) - 16.0 * TILE_SIZE).abs() < 0.0001);
+
+        assert_eq!(
+            nav.find_path(Vec2u::new(0, 0), Vec2u::new(0, 0)),
+            Some(vec![Vec2u::new(0, 0)])
+        );
+        assert_eq!(nav.find_path(Vec2u::new(0, 0), Vec2u::new(1, 1)), None);
+
+        let nav = graph(&[
+            "..#..", //
+            "..#..", //
+        ]);
+        assert_eq!(nav.find_path(Vec2u::new(0, 0), Vec2u::new(4, 0)), None);
+    }
+
+    #[test]
+    fn test_find_path_map() {
+        let tex_list_text = fs::read_to_string("data/texture_list.txt").unwrap();
+        let surfaces = parse_texture_list(&tex_list_text);
+        let map_text = fs::read_to_string("maps/Corners (4).map").unwrap();
+        let map = parse_map(&map_text, surfaces, "");
+        let nav = NavGraph::new(&map);
+
+        let start = Vec2u::new(0, 0);
+        let goal = map.size() - 1;
+        let path = nav.find_path(start, goal).unwrap();
+        for &index in &path {
+            assert_ne!(map.surface_at_index(index).kind, SurfaceKind::Wall);
+        }
+    }
+}
@@ -1,10 +1,10 @@
-//! Networking listeners and connections. TCP (remote) and mpsc (local).
+//! Networking listeners and connections. TCP, UDP or WebSocket (remote) and mpsc (local).
 //!
 //! We could use TCP locally too but WASM doesn't support it so we use mpsc.
 //!
-//! LATER Long term the plan is to switch away from TCP but honestly as long as it works, it's not a priority.
 //! The common wisdom to never use TCP for games doesn't seem to apply on modern networks.
 //! Veloren has been using TCP for years and nobody complains because nobody even notices.
+//! Still, UDP is available (see `udp`) for when packet loss makes updates stall.
 
 // This file is shared between RecWars and RustCycles
 // to keep their networking APIs the same
@@ -24,6 +24,9 @@ use serde::de::DeserializeOwned;
 
 use crate::prelude::*;
 
+pub mod udp;
+pub mod websocket;
+
 /// A trait to abstract over local and remote listeners.
 ///
 /// Note: ideally only the function would be generic over the message type
@@ -99,6 +102,14 @@ where
 {
     fn send(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error>;
 
+    /// Send a message which might get lost or arrive out of order.
+    ///
+    /// Meant for messages that are sent often and make the previous ones obsolete.
+    /// Only UDP makes use of this, other transports send it like any other message.
+    fn send_unreliable(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error> {
+        self.send(net_msg)
+    }
+
     // `#[must_use]` only does something in the trait definition,
     // no need to repeat it in the impls:
     // https://github.com/rust-lang/rust/issues/48486
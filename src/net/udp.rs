@@ -0,0 +1,746 @@
+//! UDP transport - reliable ordered and unreliable messages over one socket.
+//!
+//! Most messages (events like `Kill` or `SpawnVehicle`) have to arrive and in order.
+//! `Update`s are sent every frame and each makes the previous one obsolete
+//! so they're sent unreliably - a lost update doesn't hold up the ones after it
+//! like it would with TCP.
+//!
+//! Reliable messages are split into fragments, each with its own sequence number.
+//! The receiver acknowledges the next fragment it expects
+//! and the sender periodically resends everything not acknowledged yet.
+//! Unreliable messages are fragmented too but an incomplete one is dropped
+//! as soon as a fragment of a newer one arrives.
+//!
+//! The server uses one socket for all clients and tells them apart by address.
+
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{self, ErrorKind},
+    iter, mem,
+    net::{SocketAddr, UdpSocket},
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    net::{Connection, Listener, NetworkMessage, HEADER_LEN},
+    prelude::*,
+};
+
+/// Packets from other programs are ignored.
+const PROTOCOL_ID: u32 = 0x5257_0001;
+/// Max bytes of a message in one packet so the whole packet fits into the usual MTU of 1500.
+const FRAGMENT_LEN: usize = 1200;
+/// Max size of a packet we're willing to receive.
+const PACKET_LEN_MAX: usize = 2048;
+/// Larger messages are most likely malicious, don't buffer them.
+const MESSAGE_LEN_MAX: usize = 16 * 1024 * 1024;
+/// Reliable fragments this far ahead of the next expected one are dropped,
+/// the sender resends them later. Limits how many we buffer.
+const EARLY_WINDOW: u32 = 1024;
+/// Clients which connected but haven't sent a complete message yet.
+/// Source addresses can be spoofed so each `Connect` can't get a connection.
+const PENDING_MAX: usize = 64;
+/// Seconds before an unacknowledged reliable fragment is sent again.
+const RESEND_DELAY: f64 = 0.1;
+/// Seconds without sending anything before we let the other side know we're still here.
+const KEEPALIVE_INTERVAL: f64 = 0.5;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Packet {
+    protocol_id: u32,
+    payload: Payload,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+enum Payload {
+    /// Sent by the client repeatedly until the server accepts.
+    Connect,
+    /// Also sent again if the client keeps trying to connect because the first one got lost.
+    Accept,
+    Reliable {
+        seq: u32,
+        /// Whether this is the last fragment of a message.
+        last: bool,
+        data: Vec<u8>,
+    },
+    /// All reliable fragments before `next` have arrived.
+    Ack {
+        next: u32,
+    },
+    Unreliable {
+        id: u32,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
+    KeepAlive,
+    Disconnect,
+}
+
+#[derive(Debug)]
+struct Fragment {
+    seq: u32,
+    last: bool,
+    data: Vec<u8>,
+    sent_time: f64,
+}
+
+impl Fragment {
+    fn payload(&self) -> Payload {
+        Payload::Reliable {
+            seq: self.seq,
+            last: self.last,
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// One side of a connection - reliability, fragmentation and timeouts.
+///
+/// Doesn't touch the socket so it can be tested deterministically.
+#[derive(Debug)]
+struct Peer {
+    /// Seconds without receiving anything before the connection is considered closed.
+    timeout: f64,
+
+    next_seq: u32,
+    unacked: VecDeque<Fragment>,
+
+    next_expected: u32,
+    /// Fragments which arrived before some of the ones preceding them.
+    early: BTreeMap<u32, (bool, Vec<u8>)>,
+    /// Fragments of the reliable message being received.
+    partial: Vec<u8>,
+    ack_needed: bool,
+
+    next_unreliable_id: u32,
+    /// The newest unreliable message being received and its fragments.
+    /// The fragments are empty once it's complete.
+    assembling: Option<(u32, Vec<Option<Vec<u8>>>)>,
+
+    /// Complete messages, including the length header like in `NetworkMessage`.
+    received: VecDeque<Vec<u8>>,
+    outgoing: Vec<Payload>,
+
+    last_received: f64,
+    last_sent: f64,
+    closed: bool,
+}
+
+impl Peer {
+    fn new(now: f64, timeout: f64) -> Self {
+        Self {
+            timeout,
+            next_seq: 0,
+            unacked: VecDeque::new(),
+            next_expected: 0,
+            early: BTreeMap::new(),
+            partial: Vec::new(),
+            ack_needed: false,
+            next_unreliable_id: 0,
+            assembling: None,
+            received: VecDeque::new(),
+            outgoing: Vec::new(),
+            last_received: now,
+            last_sent: now,
+            closed: false,
+        }
+    }
+
+    fn send_reliable(&mut self, bytes: &[u8], now: f64) {
+        let chunks: Vec<_> = bytes.chunks(FRAGMENT_LEN).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let fragment = Fragment {
+                seq: self.next_seq,
+                last: i == chunks.len() - 1,
+                data: chunk.to_vec(),
+                sent_time: now,
+            };
+            self.next_seq += 1;
+            self.outgoing.push(fragment.payload());
+            self.unacked.push_back(fragment);
+        }
+    }
+
+    fn send_unreliable(&mut self, bytes: &[u8]) {
+        let id = self.next_unreliable_id;
+        self.next_unreliable_id += 1;
+
+        let chunks: Vec<_> = bytes.chunks(FRAGMENT_LEN).collect();
+        let count = u16::try_from(chunks.len()).unwrap();
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            self.outgoing.push(Payload::Unreliable {
+                id,
+                index: index as u16,
+                count,
+                data: chunk.to_vec(),
+            });
+        }
+    }
+
+    fn handle(&mut self, payload: Payload, now: f64) {
+        self.last_received = now;
+
+        match payload {
+            Payload::Connect | Payload::Accept | Payload::KeepAlive => {}
+            Payload::Reliable { seq, last, data } => {
+                // Ack even duplicates - the previous ack might have been lost.
+                self.ack_needed = true;
+                if seq >= self.next_expected && seq - self.next_expected < EARLY_WINDOW {
+                    self.early.insert(seq, (last, data));
+                }
+                while let Some((last, data)) = self.early.remove(&self.next_expected) {
+                    self.next_expected += 1;
+                    if self.partial.len() + data.len() > MESSAGE_LEN_MAX {
+                        dbg_logf!("Reliable UDP message is too long, closing connection");
+                        self.closed = true;
+                        return;
+                    }
+                    self.partial.extend(data);
+                    if last {
+                        self.received.push_back(mem::take(&mut self.partial));
+                    }
+                }
+            }
+            Payload::Ack { next } => {
+                while self
+                    .unacked
+                    .front()
+                    .is_some_and(|fragment| fragment.seq < next)
+                {
+                    self.unacked.pop_front();
+                }
+            }
+            Payload::Unreliable {
+                id,
+                index,
+                count,
+                data,
+            } => {
+                let (index, count) = (usize::from(index), usize::from(count));
+                if index >= count || count * FRAGMENT_LEN > MESSAGE_LEN_MAX {
+                    return;
+                }
+                match &self.assembling {
+                    Some((newest, _)) if id < *newest => return,
+                    Some((newest, _)) if id == *newest => {}
+                    _ => self.assembling = Some((id, vec![None; count])),
+                }
+                let (_, fragments) = self.assembling.as_mut().unwrap();
+                if fragments.len() != count {
+                    // Already complete (this is a duplicate) or malformed.
+                    return;
+                }
+                fragments[index] = Some(data);
+                if fragments.iter().all(Option::is_some) {
+                    let bytes = mem::take(fragments).into_iter().flatten().flatten();
+                    self.received.push_back(bytes.collect());
+                }
+            }
+            Payload::Disconnect => {
+                self.closed = true;
+            }
+        }
+    }
+
+    /// Acknowledge, resend, keep the connection alive and time out.
+    fn update(&mut self, now: f64) {
+        if self.ack_needed {
+            self.ack_needed = false;
+            self.outgoing.push(Payload::Ack {
+                next: self.next_expected,
+            });
+        }
+
+        for fragment in &mut self.unacked {
+            if now - fragment.sent_time >= RESEND_DELAY {
+                fragment.sent_time = now;
+                self.outgoing.push(fragment.payload());
+            }
+        }
+
+        if self.outgoing.is_empty() && now - self.last_sent >= KEEPALIVE_INTERVAL {
+            self.outgoing.push(Payload::KeepAlive);
+        }
+
+        if now - self.last_received > self.timeout {
+            if !self.closed {
+                dbg_logf!("Connection timed out");
+            }
+            self.closed = true;
+        }
+    }
+
+    fn take_outgoing(&mut self, now: f64) -> Vec<Payload> {
+        if !self.outgoing.is_empty() {
+            self.last_sent = now;
+        }
+        mem::take(&mut self.outgoing)
+    }
+}
+
+/// The server's socket shared by all its connections.
+struct SharedSocket {
+    socket: UdpSocket,
+    /// Packets for accepted clients which their connections haven't read yet.
+    inboxes: FnvHashMap<SocketAddr, Vec<Payload>>,
+    /// Clients waiting to be returned from `accept_conn`.
+    new_clients: VecDeque<SocketAddr>,
+    /// Clients which haven't sent a complete message yet.
+    pending: FnvHashSet<SocketAddr>,
+}
+
+impl SharedSocket {
+    /// Sort all available packets by sender.
+    fn pump(&mut self) {
+        for (addr, payload) in recv_all(&self.socket) {
+            let is_connect = payload == Payload::Connect;
+            if let Some(inbox) = self.inboxes.get_mut(&addr) {
+                inbox.push(payload);
+            } else if is_connect && self.pending.len() < PENDING_MAX {
+                self.inboxes.insert(addr, Vec::new());
+                self.new_clients.push_back(addr);
+                self.pending.insert(addr);
+            } else {
+                continue;
+            }
+            if is_connect {
+                send(&self.socket, Some(addr), Payload::Accept);
+            }
+        }
+    }
+}
+
+enum Socket {
+    /// Client - connected only to the server.
+    Own(UdpSocket),
+    /// Server - one socket for all clients.
+    Shared(Rc<RefCell<SharedSocket>>),
+}
+
+/// Send and receive serialized messages over the network using UDP.
+pub struct UdpConnection {
+    socket: Socket,
+    peer: Peer,
+    start: Instant,
+    pub addr: SocketAddr,
+}
+
+impl UdpConnection {
+    fn new(socket: Socket, addr: SocketAddr, timeout: f64) -> Self {
+        Self {
+            socket,
+            peer: Peer::new(0.0, timeout),
+            start: Instant::now(),
+            addr,
+        }
+    }
+
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+
+    /// Handle all packets that arrived and send what's needed in response.
+    fn poll(&mut self) {
+        let now = self.now();
+        let payloads: Vec<_> = match &self.socket {
+            Socket::Own(socket) => recv_all(socket)
+                .into_iter()
+                .filter(|(addr, _)| *addr == self.addr)
+                .map(|(_, payload)| payload)
+                .collect(),
+            Socket::Shared(shared) => {
+                let mut shared = shared.borrow_mut();
+                shared.pump();
+                shared
+                    .inboxes
+                    .get_mut(&self.addr)
+                    .map(mem::take)
+                    .unwrap_or_default()
+            }
+        };
+        for payload in payloads {
+            self.peer.handle(payload, now);
+        }
+        if let Socket::Shared(shared) = &self.socket {
+            if !self.peer.received.is_empty() {
+                shared.borrow_mut().pending.remove(&self.addr);
+            }
+        }
+        self.peer.update(now);
+        self.flush(now);
+    }
+
+    fn flush(&mut self, now: f64) {
+        for payload in self.peer.take_outgoing(now) {
+            match &self.socket {
+                Socket::Own(socket) => send(socket, None, payload),
+                Socket::Shared(shared) => send(&shared.borrow().socket, Some(self.addr), payload),
+            }
+        }
+    }
+
+    fn parse_one<M>(&mut self) -> Option<M>
+    where
+        M: DeserializeOwned,
+    {
+        loop {
+            let bytes = self.peer.received.pop_front()?;
+            match bincode::deserialize(&bytes[HEADER_LEN.min(bytes.len())..]) {
+                Ok(msg) => return Some(msg),
+                Err(e) => dbg_logf!(
+                    "WARNING: failed to deserialize message from {}: {e}",
+                    self.addr
+                ),
+            }
+        }
+    }
+
+    fn closed_error(&self) -> io::Error {
+        io::Error::new(
+            ErrorKind::ConnectionAborted,
+            "connection closed or timed out",
+        )
+    }
+}
+
+impl<M> Connection<M> for UdpConnection
+where
+    M: DeserializeOwned,
+{
+    fn send(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error> {
+        if self.peer.closed {
+            return Err(self.closed_error());
+        }
+        let now = self.now();
+        self.peer.send_reliable(&net_msg.bytes, now);
+        self.flush(now);
+        Ok(())
+    }
+
+    fn send_unreliable(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error> {
+        if self.peer.closed {
+            return Err(self.closed_error());
+        }
+        let now = self.now();
+        self.peer.send_unreliable(&net_msg.bytes);
+        self.flush(now);
+        Ok(())
+    }
+
+    fn receive(&mut self) -> (Vec<M>, bool) {
+        self.poll();
+        let msgs = iter::from_fn(|| self.parse_one()).collect();
+        (msgs, self.peer.closed)
+    }
+
+    fn receive_one(&mut self) -> (Option<M>, bool) {
+        self.poll();
+        let msg = self.parse_one();
+        (msg, self.peer.closed)
+    }
+
+    fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+}
+
+impl Drop for UdpConnection {
+    fn drop(&mut self) {
+        // Best effort, if it gets lost, the other side times out.
+        match &self.socket {
+            Socket::Own(socket) => send(socket, None, Payload::Disconnect),
+            Socket::Shared(shared) => {
+                let mut shared = shared.borrow_mut();
+                send(&shared.socket, Some(self.addr), Payload::Disconnect);
+                shared.inboxes.remove(&self.addr);
+                shared.pending.remove(&self.addr);
+            }
+        }
+    }
+}
+
+pub struct UdpListener {
+    shared: Rc<RefCell<SharedSocket>>,
+    /// Seconds without hearing from a client before it's disconnected.
+    timeout: f64,
+}
+
+impl UdpListener {
+    pub fn bind(addr: &str, timeout: f64) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        let shared = SharedSocket {
+            socket,
+            inboxes: FnvHashMap::default(),
+            new_clients: VecDeque::new(),
+            pending: FnvHashSet::default(),
+        };
+        Ok(Self {
+            shared: Rc::new(RefCell::new(shared)),
+            timeout,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.shared.borrow().socket.local_addr()
+    }
+}
+
+impl<M> Listener<M> for UdpListener
+where
+    M: DeserializeOwned,
+{
+    fn accept_conn(&mut self) -> io::Result<Box<dyn Connection<M>>> {
+        let mut shared = self.shared.borrow_mut();
+        shared.pump();
+        let Some(addr) = shared.new_clients.pop_front() else {
+            return Err(io::Error::new(ErrorKind::WouldBlock, "no new clients"));
+        };
+        drop(shared);
+
+        let socket = Socket::Shared(Rc::clone(&self.shared));
+        let conn = UdpConnection::new(socket, addr, self.timeout);
+        Ok(Box::new(conn))
+    }
+}
+
+/// Blocks until the server accepts, same as `tcp_connect_blocking`.
+pub fn udp_connect_blocking(cvars: &Cvars, addr: &str) -> UdpConnection {
+    let addr = SocketAddr::from_str(addr).unwrap();
+    let local_addr = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(local_addr).unwrap();
+    socket.connect(addr).unwrap();
+    socket.set_nonblocking(true).unwrap();
+
+    let mut connect_attempts = 0;
+    loop {
+        connect_attempts += 1;
+        // LATER Limit the number of attempts.
+        send(&socket, None, Payload::Connect);
+        thread::sleep(Duration::from_millis(cvars.cl_net_connect_retry_delay_ms));
+        // Anything else the server sent in the meantime is reliable and will be resent.
+        let packets = recv_all(&socket);
+        if packets
+            .iter()
+            .any(|(_, payload)| *payload == Payload::Accept)
+        {
+            dbg_logf!("connect attempts: {}", connect_attempts);
+            break;
+        }
+        if connect_attempts % cvars.cl_net_connect_retry_print_every_n == 0 {
+            dbg_logf!("connect attempts: {}", connect_attempts);
+        }
+    }
+
+    UdpConnection::new(Socket::Own(socket), addr, cvars.cl_net_timeout)
+}
+
+/// Lost packets are handled by resending so errors are only logged.
+fn send(socket: &UdpSocket, addr: Option<SocketAddr>, payload: Payload) {
+    let packet = Packet {
+        protocol_id: PROTOCOL_ID,
+        payload,
+    };
+    let bytes = bincode::serialize(&packet).expect("bincode failed to serialize packet");
+    let res = match addr {
+        Some(addr) => socket.send_to(&bytes, addr),
+        None => socket.send(&bytes),
+    };
+    match res {
+        Ok(_) => {}
+        Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+        Err(e) => dbg_logf!("UDP error when sending: {e}"),
+    }
+}
+
+/// Read all packets available on the socket, ignore those which are not ours.
+fn recv_all(socket: &UdpSocket) -> Vec<(SocketAddr, Payload)> {
+    let mut packets = Vec::new();
+    let mut buf = [0; PACKET_LEN_MAX];
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((n, addr)) => {
+                if let Ok(Packet {
+                    protocol_id: PROTOCOL_ID,
+                    payload,
+                }) = bincode::deserialize(&buf[..n])
+                {
+                    packets.push((addr, payload));
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return packets,
+            // E.g. the other side isn't listening (anymore).
+            // Not fatal, it'll time out if it doesn't come back.
+            Err(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net;
+
+    use super::*;
+
+    /// Deliver everything `from` wants to send, except for a third of the packets.
+    fn transfer(from: &mut Peer, to: &mut Peer, now: f64, rng: &mut Xoshiro256PlusPlus) {
+        for payload in from.take_outgoing(now) {
+            if rng.gen_bool(0.67) {
+                to.handle(payload, now);
+            }
+        }
+    }
+
+    #[test]
+    fn test_peer_lossy() {
+        let mut a = Peer::new(0.0, 1.0);
+        let mut b = Peer::new(0.0, 1.0);
+        let big: Vec<u8> = (0..5000).map(|i| i as u8).collect();
+        a.send_reliable(&big, 0.0);
+        a.send_reliable(&[1, 2, 3], 0.0);
+        a.send_reliable(&[4], 0.0);
+
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut now = 0.0;
+        while now < 0.9 {
+            a.update(now);
+            transfer(&mut a, &mut b, now, &mut rng);
+            b.update(now);
+            transfer(&mut b, &mut a, now, &mut rng);
+            now += 0.05;
+        }
+
+        // Everything arrived, in order and exactly once.
+        let received: Vec<_> = b.received.drain(..).collect();
+        assert_eq!(received, vec![big, vec![1, 2, 3], vec![4]]);
+        assert!(a.unacked.is_empty());
+        assert!(!a.closed && !b.closed);
+
+        // Only complete unreliable messages are received, older ones are dropped.
+        a.send_unreliable(&[5; 3000]);
+        let first = a.take_outgoing(now);
+        a.send_unreliable(&[6; 3000]);
+        let second = a.take_outgoing(now);
+        b.handle(first[0].clone(), now);
+        for payload in second.clone() {
+            b.handle(payload, now);
+        }
+        for payload in first.into_iter().chain(second) {
+            b.handle(payload, now);
+        }
+        let received: Vec<_> = b.received.drain(..).collect();
+        assert_eq!(received, vec![vec![6; 3000]]);
+
+        a.update(now + 2.0);
+        assert!(a.closed);
+    }
+
+    #[test]
+    fn test_peer_limits() {
+        let mut peer = Peer::new(0.0, 1.0);
+        let reliable = |seq, last, data| Payload::Reliable { seq, last, data };
+
+        // Fragments too far ahead are not buffered.
+        peer.handle(reliable(EARLY_WINDOW - 1, true, vec![1]), 0.0);
+        peer.handle(reliable(EARLY_WINDOW, true, vec![2]), 0.0);
+        peer.handle(reliable(u32::MAX, true, vec![3]), 0.0);
+        assert_eq!(peer.early.len(), 1);
+
+        // Unreliable messages which would be too long are ignored.
+        let count = (MESSAGE_LEN_MAX / FRAGMENT_LEN + 1) as u16;
+        let unreliable = Payload::Unreliable {
+            id: 0,
+            index: 0,
+            count,
+            data: vec![4],
+        };
+        peer.handle(unreliable, 0.0);
+        assert!(peer.assembling.is_none());
+
+        // Reliable messages which are too long close the connection.
+        peer.handle(reliable(0, false, vec![0; MESSAGE_LEN_MAX]), 0.0);
+        assert!(!peer.closed);
+        peer.handle(reliable(1, false, vec![0]), 0.0);
+        assert!(peer.closed);
+        assert!(peer.received.is_empty());
+    }
+
+    #[test]
+    fn test_loopback() {
+        let mut listener = UdpListener::bind("127.0.0.1:0", 5.0).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let cvars = Cvars::default();
+
+        // Can't move the listener to another thread so the client gets one instead.
+        let client = thread::spawn(move || {
+            let mut conn = udp_connect_blocking(&cvars, &addr);
+            let big = "x".repeat(5000);
+            Connection::<String>::send(&mut conn, &net::serialize(big)).unwrap();
+            Connection::<String>::send_unreliable(&mut conn, &net::serialize("u")).unwrap();
+            loop {
+                let (msgs, closed): (Vec<String>, _) = conn.receive();
+                assert!(!closed);
+                if !msgs.is_empty() {
+                    return msgs;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let start = Instant::now();
+        let mut conn: Box<dyn Connection<String>> = loop {
+            if let Ok(conn) = listener.accept_conn() {
+                break conn;
+            }
+            assert!(start.elapsed().as_secs() < 5);
+            thread::sleep(Duration::from_millis(1));
+        };
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let (msgs, closed) = conn.receive();
+            assert!(!closed);
+            received.extend(msgs);
+            assert!(start.elapsed().as_secs() < 5);
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(received, vec!["x".repeat(5000), "u".to_owned()]);
+
+        conn.send(&net::serialize("bye")).unwrap();
+        assert_eq!(client.join().unwrap(), vec!["bye".to_owned()]);
+    }
+
+    #[test]
+    fn test_pending_limit() {
+        let listener = UdpListener::bind("127.0.0.1:0", 5.0).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Keep the sockets open until the end.
+        let _clients: Vec<_> = (0..PENDING_MAX + 1)
+            .map(|_| {
+                let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+                socket.connect(addr).unwrap();
+                send(&socket, None, Payload::Connect);
+                socket
+            })
+            .collect();
+
+        let start = Instant::now();
+        let mut shared = listener.shared.borrow_mut();
+        while start.elapsed().as_secs_f64() < 0.5 {
+            shared.pump();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(shared.new_clients.len(), PENDING_MAX);
+    }
+}
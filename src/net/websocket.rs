@@ -0,0 +1,688 @@
+//! WebSocket transport so browser builds can connect to native servers.
+//!
+//! Browsers can't open raw TCP sockets, only WebSockets,
+//! which are TCP with an HTTP handshake and framing on top (RFC 6455).
+//! Each `NetworkMessage` is sent as one binary message.
+//!
+//! The server side and the native client (mostly for testing) are implemented here directly.
+//! The browser client uses the browser's WebSocket API through `websocket.js`.
+
+use std::{
+    io::{self, ErrorKind, Write},
+    mem,
+    net::{SocketAddr, TcpListener, TcpStream},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    net::{self, Connection, Listener, NetworkMessage, HEADER_LEN},
+    prelude::*,
+};
+
+/// Appended to the client's key to prove the server understands WebSockets.
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Clients which don't finish the handshake in time are dropped.
+const HANDSHAKE_TIMEOUT: f64 = 5.0;
+/// Longer requests are most likely not from our clients.
+const HANDSHAKE_LEN_MAX: usize = 8192;
+/// Larger messages are most likely malicious, don't buffer them.
+const MESSAGE_LEN_MAX: usize = 16 * 1024 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Accepts TCP connections and upgrades them to WebSocket.
+pub struct WsListener {
+    listener: TcpListener,
+    /// Connections which haven't finished the HTTP handshake yet.
+    handshakes: Vec<Handshake>,
+}
+
+impl WsListener {
+    pub fn bind(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            handshakes: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+impl<M> Listener<M> for WsListener
+where
+    M: DeserializeOwned,
+{
+    fn accept_conn(&mut self) -> io::Result<Box<dyn Connection<M>>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    stream.set_nodelay(true).unwrap();
+                    stream.set_nonblocking(true).unwrap();
+                    self.handshakes.push(Handshake {
+                        stream,
+                        addr,
+                        request: VecDeque::new(),
+                        start: Instant::now(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+
+        // The request might not arrive all at once so each call continues where the last one stopped.
+        let mut i = 0;
+        while i < self.handshakes.len() {
+            match self.handshakes[i].progress() {
+                Ok(false) => i += 1,
+                Ok(true) => {
+                    let Handshake {
+                        stream,
+                        addr,
+                        request,
+                        ..
+                    } = self.handshakes.swap_remove(i);
+                    // Clients can't send anything before the server responds
+                    // so anything after the request would be garbage.
+                    if request.is_empty() {
+                        let conn = WsConnection::new(stream, addr, VecDeque::new(), false);
+                        return Ok(Box::new(conn));
+                    }
+                    dbg_logf!("WebSocket handshake with {addr} failed: data after request");
+                }
+                Err(reason) => {
+                    let addr = self.handshakes[i].addr;
+                    dbg_logf!("WebSocket handshake with {addr} failed: {reason}");
+                    self.handshakes.swap_remove(i);
+                }
+            }
+        }
+
+        Err(io::Error::new(
+            ErrorKind::WouldBlock,
+            "no finished handshakes",
+        ))
+    }
+}
+
+struct Handshake {
+    stream: TcpStream,
+    addr: SocketAddr,
+    request: VecDeque<u8>,
+    start: Instant,
+}
+
+impl Handshake {
+    /// Read the HTTP request and respond once it's complete.
+    ///
+    /// Returns whether the handshake is done.
+    /// After that, `request` contains only what came after the request.
+    fn progress(&mut self) -> Result<bool, String> {
+        let closed = net::read(&mut self.stream, &mut self.request);
+        if closed {
+            return Err("connection closed".to_owned());
+        }
+        if self.start.elapsed().as_secs_f64() > HANDSHAKE_TIMEOUT {
+            return Err("timed out".to_owned());
+        }
+
+        let received = self.request.make_contiguous();
+        let Some(headers_len) = headers_len(received) else {
+            if received.len() > HANDSHAKE_LEN_MAX {
+                return Err("request too long".to_owned());
+            }
+            return Ok(false);
+        };
+        let request = String::from_utf8_lossy(&received[..headers_len]);
+        let key = header(&request, "sec-websocket-key").ok_or("missing key")?;
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\
+            Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        );
+        self.request.drain(..headers_len);
+        self.stream
+            .write_all(response.as_bytes())
+            .map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+}
+
+/// Send and receive serialized messages over the network using WebSocket.
+pub struct WsConnection {
+    stream: TcpStream,
+    buffer: VecDeque<u8>,
+    /// Payload of a message split into multiple frames received so far.
+    partial: Vec<u8>,
+    /// Clients have to mask what they send, servers must not.
+    rng: Option<Xoshiro256PlusPlus>,
+    closed: bool,
+    pub addr: SocketAddr,
+}
+
+impl WsConnection {
+    fn new(stream: TcpStream, addr: SocketAddr, buffer: VecDeque<u8>, client: bool) -> Self {
+        let rng = client.then(|| Xoshiro256PlusPlus::seed_from_u64(time_seed()));
+        Self {
+            stream,
+            buffer,
+            partial: Vec::new(),
+            rng,
+            closed: false,
+            addr,
+        }
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mask = self.rng.as_mut().map(|rng| rng.gen::<[u8; 4]>());
+        let frame = encode_frame(opcode, payload, mask);
+        self.stream.write_all(&frame)?;
+        self.stream.flush()
+    }
+
+    /// Parse the next complete message, handle control frames along the way.
+    fn parse_one<M>(&mut self) -> Option<M>
+    where
+        M: DeserializeOwned,
+    {
+        // Clients don't have an RNG so they only receive from the server.
+        let require_mask = self.rng.is_none();
+        loop {
+            let (fin, opcode, payload) = match decode_frame(&mut self.buffer, require_mask) {
+                Ok(Some(frame)) => frame,
+                Ok(None) => return None,
+                Err(e) => {
+                    dbg_logf!("Invalid WebSocket frame from {}: {e}", self.addr);
+                    self.closed = true;
+                    return None;
+                }
+            };
+            match opcode {
+                OPCODE_CONTINUATION | OPCODE_TEXT | OPCODE_BINARY => {
+                    if self.partial.len() + payload.len() > MESSAGE_LEN_MAX {
+                        dbg_logf!("WebSocket message from {} is too long", self.addr);
+                        self.closed = true;
+                        return None;
+                    }
+                    self.partial.extend(payload);
+                    if fin {
+                        let bytes = mem::take(&mut self.partial);
+                        match bincode::deserialize(&bytes[HEADER_LEN.min(bytes.len())..]) {
+                            Ok(msg) => return Some(msg),
+                            Err(e) => {
+                                dbg_logf!(
+                                    "WARNING: failed to deserialize message from {}: {e}",
+                                    self.addr
+                                )
+                            }
+                        }
+                    }
+                }
+                OPCODE_CLOSE => {
+                    // Best effort, we're closing anyway.
+                    let _ = self.send_frame(OPCODE_CLOSE, &payload);
+                    self.closed = true;
+                    return None;
+                }
+                OPCODE_PING => {
+                    if self.send_frame(OPCODE_PONG, &payload).is_err() {
+                        self.closed = true;
+                    }
+                }
+                OPCODE_PONG => {}
+                _ => {
+                    dbg_logf!("Unknown WebSocket opcode {opcode} from {}", self.addr);
+                    self.closed = true;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<M> Connection<M> for WsConnection
+where
+    M: DeserializeOwned,
+{
+    fn send(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error> {
+        self.send_frame(OPCODE_BINARY, &net_msg.bytes)
+    }
+
+    fn receive(&mut self) -> (Vec<M>, bool) {
+        let closed = net::read(&mut self.stream, &mut self.buffer);
+        let msgs = std::iter::from_fn(|| self.parse_one()).collect();
+        (msgs, closed || self.closed)
+    }
+
+    fn receive_one(&mut self) -> (Option<M>, bool) {
+        let closed = net::read(&mut self.stream, &mut self.buffer);
+        let msg = self.parse_one();
+        (msg, closed || self.closed)
+    }
+
+    fn addr(&self) -> String {
+        self.addr.to_string()
+    }
+}
+
+/// Connect to a `ws://` URL. Blocks until the handshake is done, same as `tcp_connect_blocking`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn ws_connect(cvars: &Cvars, url: &str) -> WsConnection {
+    use std::{io::Read, thread, time::Duration};
+
+    let host = url.strip_prefix("ws://").unwrap_or(url);
+    let host = host.split('/').next().unwrap();
+    let addr = SocketAddr::from_str(host).unwrap();
+
+    let mut connect_attempts = 0;
+    let mut stream = loop {
+        connect_attempts += 1;
+        // LATER Limit the number of attempts.
+        if let Ok(stream) = TcpStream::connect(addr) {
+            dbg_logf!("connect attempts: {}", connect_attempts);
+            break stream;
+        }
+        if connect_attempts % cvars.cl_net_connect_retry_print_every_n == 0 {
+            dbg_logf!("connect attempts: {}", connect_attempts);
+        }
+        thread::sleep(Duration::from_millis(cvars.cl_net_connect_retry_delay_ms));
+    };
+    stream.set_nodelay(true).unwrap();
+
+    let mut rng = Xoshiro256PlusPlus::seed_from_u64(time_seed());
+    let key = base64(&rng.gen::<[u8; 16]>());
+    let request = format!(
+        "GET / HTTP/1.1\r\n\
+        Host: {host}\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Key: {key}\r\n\
+        Sec-WebSocket-Version: 13\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).unwrap();
+
+    // The server might send messages right after the response
+    // so everything after it is kept for later.
+    let mut received = Vec::new();
+    let headers_len = loop {
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).unwrap();
+        assert!(n > 0, "server closed the connection during handshake");
+        received.extend(&buf[..n]);
+        if let Some(len) = headers_len(&received) {
+            break len;
+        }
+    };
+    let response = String::from_utf8_lossy(&received[..headers_len]);
+    assert!(
+        response.starts_with("HTTP/1.1 101"),
+        "server refused WebSocket: {response}"
+    );
+    assert_eq!(
+        header(&response, "sec-websocket-accept"),
+        Some(accept_key(&key).as_str()),
+        "server sent a wrong accept key"
+    );
+    stream.set_nonblocking(true).unwrap();
+
+    let buffer = received[headers_len..].iter().copied().collect();
+    WsConnection::new(stream, addr, buffer, true)
+}
+
+/// Length of the HTTP headers including the empty line at the end, if they're complete.
+fn headers_len(bytes: &[u8]) -> Option<usize> {
+    bytes
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| pos + 4)
+}
+
+fn header<'a>(headers: &'a str, name: &str) -> Option<&'a str> {
+    headers.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{HANDSHAKE_GUID}").as_bytes()))
+}
+
+fn time_seed() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    since_epoch.as_nanos() as u64
+}
+
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    if payload.len() < 126 {
+        frame.push(mask_bit | payload.len() as u8);
+    } else if let Ok(len) = u16::try_from(payload.len()) {
+        frame.push(mask_bit | 126);
+        frame.extend(len.to_be_bytes());
+    } else {
+        frame.push(mask_bit | 127);
+        frame.extend((payload.len() as u64).to_be_bytes());
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend(mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        }
+        None => frame.extend(payload),
+    }
+    frame
+}
+
+/// Parse a frame from `buffer` or return None if there's not enough data.
+///
+/// Returns whether it's the final frame of a message, its opcode and unmasked payload.
+/// RFC 6455 requires frames sent by clients to be masked,
+/// the server sets `require_mask` to reject those which aren't.
+fn decode_frame(
+    buffer: &mut VecDeque<u8>,
+    require_mask: bool,
+) -> Result<Option<(bool, u8, Vec<u8>)>, String> {
+    if buffer.len() < 2 {
+        return Ok(None);
+    }
+    let fin = buffer[0] & 0x80 != 0;
+    let opcode = buffer[0] & 0x0F;
+    let masked = buffer[1] & 0x80 != 0;
+    if require_mask && !masked {
+        return Err("unmasked frame".to_owned());
+    }
+    let (payload_len, mut header_len): (u64, usize) = match buffer[1] & 0x7F {
+        126 => {
+            let bytes = buffer.range(2..).take(2).copied().collect::<Vec<_>>();
+            let Ok(bytes) = <[u8; 2]>::try_from(bytes) else {
+                return Ok(None);
+            };
+            (u64::from(u16::from_be_bytes(bytes)), 4)
+        }
+        127 => {
+            let bytes = buffer.range(2..).take(8).copied().collect::<Vec<_>>();
+            let Ok(bytes) = <[u8; 8]>::try_from(bytes) else {
+                return Ok(None);
+            };
+            (u64::from_be_bytes(bytes), 10)
+        }
+        len => (u64::from(len), 2),
+    };
+    let payload_len = match usize::try_from(payload_len) {
+        Ok(len) if len <= MESSAGE_LEN_MAX => len,
+        _ => return Err(format!("payload too long ({payload_len} bytes)")),
+    };
+    let mask_start = header_len;
+    if masked {
+        header_len += 4;
+    }
+    let frame_len = header_len
+        .checked_add(payload_len)
+        .ok_or_else(|| "frame length overflow".to_owned())?;
+    if buffer.len() < frame_len {
+        return Ok(None);
+    }
+
+    let mask: Vec<_> = buffer.range(mask_start..header_len).copied().collect();
+    buffer.drain(..header_len);
+    let mut payload: Vec<_> = buffer.drain(..payload_len).collect();
+    if masked {
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+    }
+    Ok(Some((fin, opcode, payload)))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3F;
+                encoded.push(ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Only used for the handshake, not for anything security related.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes(block[i * 4..i * 4 + 4].try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (i, word) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Uses the browser's WebSocket API through functions defined in `websocket.js`.
+#[cfg(target_arch = "wasm32")]
+pub mod browser {
+    use super::*;
+
+    extern "C" {
+        fn rw_ws_connect(url: *const u8, url_len: usize) -> u32;
+        fn rw_ws_send(id: u32, data: *const u8, len: usize);
+        fn rw_ws_closed(id: u32) -> u32;
+        fn rw_ws_next_len(id: u32) -> i32;
+        fn rw_ws_take(id: u32, buf: *mut u8);
+    }
+
+    pub struct BrowserWsConnection {
+        id: u32,
+        url: String,
+    }
+
+    impl<M> Connection<M> for BrowserWsConnection
+    where
+        M: DeserializeOwned,
+    {
+        fn send(&mut self, net_msg: &NetworkMessage) -> Result<(), io::Error> {
+            // Messages sent before the connection opens are queued by the browser glue.
+            unsafe { rw_ws_send(self.id, net_msg.bytes.as_ptr(), net_msg.bytes.len()) };
+            Ok(())
+        }
+
+        fn receive(&mut self) -> (Vec<M>, bool) {
+            let mut msgs = Vec::new();
+            loop {
+                let (msg, closed) = self.receive_one();
+                match msg {
+                    Some(msg) => msgs.push(msg),
+                    None => return (msgs, closed),
+                }
+            }
+        }
+
+        fn receive_one(&mut self) -> (Option<M>, bool) {
+            let closed = unsafe { rw_ws_closed(self.id) } != 0;
+            let len = unsafe { rw_ws_next_len(self.id) };
+            if len < 0 {
+                return (None, closed);
+            }
+            let mut bytes = vec![0; len as usize];
+            unsafe { rw_ws_take(self.id, bytes.as_mut_ptr()) };
+            match bincode::deserialize(&bytes[HEADER_LEN.min(bytes.len())..]) {
+                Ok(msg) => (Some(msg), closed),
+                Err(e) => {
+                    dbg_logf!("WARNING: failed to deserialize message: {e}");
+                    (None, closed)
+                }
+            }
+        }
+
+        fn addr(&self) -> String {
+            self.url.clone()
+        }
+    }
+
+    /// Doesn't block (browsers don't allow it), the connection opens in the background.
+    pub fn ws_connect(_cvars: &Cvars, url: &str) -> BrowserWsConnection {
+        let id = unsafe { rw_ws_connect(url.as_ptr(), url.len()) };
+        BrowserWsConnection {
+            id,
+            url: url.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_handshake() {
+        // Example from RFC 6455.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+
+        let request = "GET / HTTP/1.1\r\nSec-WebSocket-Key: abc \r\n\r\n";
+        assert_eq!(headers_len(request.as_bytes()), Some(request.len()));
+        assert_eq!(header(request, "sec-websocket-key"), Some("abc"));
+    }
+
+    #[test]
+    fn test_frames() {
+        for len in [0, 125, 126, 70_000] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            for mask in [None, Some([1, 2, 3, 4])] {
+                let frame = encode_frame(OPCODE_BINARY, &payload, mask);
+                let mut buffer: VecDeque<u8> = frame[..frame.len() - 1].iter().copied().collect();
+                assert_eq!(decode_frame(&mut buffer, false), Ok(None));
+                buffer.push_back(frame[frame.len() - 1]);
+                assert_eq!(
+                    decode_frame(&mut buffer, false),
+                    Ok(Some((true, OPCODE_BINARY, payload.clone())))
+                );
+                assert!(buffer.is_empty());
+            }
+        }
+
+        let frame = encode_frame(OPCODE_BINARY, b"abc", None);
+        let mut buffer: VecDeque<u8> = frame.into_iter().collect();
+        assert!(decode_frame(&mut buffer, true).is_err());
+
+        let mut buffer: VecDeque<u8> = [0x82, 0x80 | 127].into_iter().collect();
+        buffer.extend(u64::MAX.to_be_bytes());
+        assert!(decode_frame(&mut buffer, true).is_err());
+    }
+
+    #[test]
+    fn test_echo() {
+        let mut listener = WsListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let cvars = Cvars::default();
+
+        let client = thread::spawn(move || {
+            let mut conn = ws_connect(&cvars, &url);
+            let msg = "x".repeat(70_000);
+            Connection::<String>::send(&mut conn, &net::serialize(msg)).unwrap();
+            loop {
+                let (msg, closed): (Option<String>, _) = conn.receive_one();
+                assert!(!closed);
+                if let Some(msg) = msg {
+                    return msg;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let start = Instant::now();
+        let mut conn: Box<dyn Connection<String>> = loop {
+            if let Ok(conn) = listener.accept_conn() {
+                break conn;
+            }
+            assert!(start.elapsed().as_secs() < 5);
+            thread::sleep(Duration::from_millis(1));
+        };
+        let msg = loop {
+            let (msg, closed) = conn.receive_one();
+            assert!(!closed);
+            if let Some(msg) = msg {
+                break msg;
+            }
+            assert!(start.elapsed().as_secs() < 5);
+            thread::sleep(Duration::from_millis(1));
+        };
+        conn.send(&net::serialize(&msg)).unwrap();
+
+        assert_eq!(client.join().unwrap(), msg);
+    }
+}
@@ -9,17 +9,34 @@
 
 use crate::{
     debug::details::{DebugShape, WorldText},
+    delta::{PhysicsUpdate, QuantizedAngle, RemovedEntities},
+    map::SurfaceKind,
     prelude::*,
 };
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ClientMessage {
+    /// Must be the first message on every connection.
     Version(Version),
     Connect(Connect),
     Input(NetInput),
-    Chat(String), // LATER Allow sending this
+    /// Input numbered so the server can acknowledge it in `Update`,
+    /// the client uses this for prediction.
+    /// Bot controllers use plain `Input` instead.
+    SequencedInput {
+        seq: u32,
+        net_input: NetInput,
+        /// The last `Update` received, the server sends only changes since then.
+        last_update: Option<usize>,
+    },
+    /// Sent after connecting and whenever the player changes it.
+    VehiclePreference(VehiclePreference),
+    /// A line of chat, the server sends it to everyone as `ServerMessage::Chat`.
+    Chat(String),
     Pause,
+    /// Stop observing and get a vehicle.
     Join,
+    /// Give up the vehicle and fly around as a free camera.
     Observe,
 }
 
@@ -27,7 +44,7 @@ pub enum ClientMessage {
 ///
 /// This struct must remain stable across all versions
 /// so old versions can parse the message from new versions.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Version {
     /// The name of the game, for example "RecWars" or "RustCycles".
     /// Since they use very similar protocols, this is used to make sure
@@ -56,11 +73,60 @@ pub struct Version {
     pub extra: Option<String>,
 }
 
+impl Version {
+    /// The version of this build.
+    pub fn current() -> Self {
+        let pre = env!("CARGO_PKG_VERSION_PRE");
+        Self {
+            game: "RecWars".to_owned(),
+            major: env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap(),
+            minor: env!("CARGO_PKG_VERSION_MINOR").parse().unwrap(),
+            patch: env!("CARGO_PKG_VERSION_PATCH").parse().unwrap(),
+            pre: (!pre.is_empty()).then(|| pre.to_owned()),
+            commits: None,
+            hash: None,
+            dirty: None,
+            extra: Some(env!("GIT_VERSION").to_owned()),
+        }
+    }
+
+    /// Whether the client and server can play together, if not, why.
+    ///
+    /// Different patch versions are compatible,
+    /// anything more might have changed the messages.
+    pub fn check_compatible(client: &Version, server: &Version) -> Result<(), String> {
+        if client.game != server.game {
+            Err(format!(
+                "The server is running {}, not {}",
+                server.game, client.game
+            ))
+        } else if client.major != server.major || client.minor != server.minor {
+            Err(format!(
+                "Client version {} is not compatible with server version {}",
+                client, server
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{pre}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Connect {
-    pub cl_version: String, // TODO remove
     pub name1: String,
     pub name2: Option<String>,
+    /// Sent here so the first vehicle is already the preferred one.
+    pub vehicle: VehiclePreference,
 }
 
 // #[derive(Debug, Deserialize, Serialize)]
@@ -83,8 +149,15 @@ pub struct Connect {
 /// The recommended usage when receiving is to destructure the data so you notice when new fields are added.
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ServerMessage {
+    /// The reply to the client's `Version` if they're compatible.
     Version(Version),
 
+    /// The client's version is not compatible (or it didn't send it), the connection will be closed.
+    ///
+    /// Like `Version`, this must stay the same in all versions
+    /// so clients can always show why they can't connect.
+    Rejected(String),
+
     /// Initial game state that is sent to a new player upon connecting.
     ///
     /// This is intentionally separate from messages such as AddPlayer or SpawnVehicle
@@ -102,6 +175,7 @@ pub enum ServerMessage {
     SpawnVehicle(VehicleInit),
     SpawnProjectile(ProjectileInit),
     SpawnExplosion(ExplosionInit),
+    SpawnMine(MineInit),
 
     RailBeam(RailBeam),
 
@@ -113,23 +187,83 @@ pub enum ServerMessage {
     DestroyProjectile {
         index: u32,
     },
+    /// Remove the mine. If it was triggered, the explosion is sent separately.
+    DestroyMine {
+        index: u32,
+    },
 
     Kill(Kill),
+
+    /// The player shot before spawn protection ran out.
+    ProtectionEnd {
+        /// Vehicle index
+        index: u32,
+    },
+
+    /// Capture The Cow - the player picked up the cow.
+    CowPickup {
+        player: u32,
+    },
+    /// Capture The Cow - the carrier died or left.
+    CowDrop {
+        pos: Vec2f,
+    },
+    /// Capture The Cow - the carrier reached their team's base. The cow goes back to its spawn.
+    CowCapture {
+        player: u32,
+    },
+    /// Capture The Cow - nobody picked up the dropped cow in time so it went back to its spawn.
+    CowReturn,
+
+    /// A time or score limit was reached, the intermission starts.
+    MatchEnd(MatchEnd),
+    /// The intermission is over, scores are reset.
+    /// Everyone gets a new vehicle via SpawnVehicle.
+    MatchStart(MatchStart),
+
+    /// What an external bot controller sees. Sent every server frame.
+    ///
+    /// Controllers receive only this message, never `Init`, `Update` or events.
+    Observation(Observation),
+
+    /// The player sent a new name.
+    /// The server also announces it in chat.
+    RenamePlayer {
+        index: u32,
+        name: String,
+    },
+    Chat(ChatMessage),
+
+    /// The player became an observer.
+    /// Their vehicle, projectiles and mines are removed and their score is reset.
+    PlayerObserve {
+        index: u32,
+    },
+    /// The observer started playing. Their vehicle follows in `SpawnVehicle`.
+    PlayerJoin {
+        index: u32,
+        team: Option<Team>,
+    },
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Init {
-    pub sv_version: String,
     pub map_path: String,
     pub frame_num: usize,
     pub game_time: f64,
     pub game_time_prev: f64,
     pub dt: f64,
+    pub time_limit: f64,
+    pub game_mode: GameMode,
+    pub match_state: MatchState,
+    pub team_scores: [TeamScore; 2],
     pub players: Vec<PlayerInit>,
     pub local_player1_index: u32,
     pub local_player2_index: Option<u32>,
     pub vehicles: Vec<VehicleInit>,
     pub projectiles: Vec<ProjectileInit>,
+    pub mines: Vec<MineInit>,
+    pub cow: Option<CowInit>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -137,6 +271,8 @@ pub struct PlayerInit {
     pub index: u32,
     pub name: String,
     pub score: Score,
+    pub team: Option<Team>,
+    pub observing: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -147,6 +283,7 @@ pub struct VehicleInit {
     pub turret_angle_current: f64,
     pub turret_angle_wanted: f64,
     pub spawn_time: f64,
+    pub protection_end: f64,
     pub owner: u32,
 }
 
@@ -159,6 +296,20 @@ pub struct ProjectileInit {
     pub owner: u32,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MineInit {
+    pub index: u32,
+    pub pos: Vec2f,
+    pub arm_time: f64,
+    pub owner: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CowInit {
+    pub pos: Vec2f,
+    pub carrier: Option<u32>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ExplosionInit {
     pub pos: Vec2f,
@@ -172,33 +323,93 @@ pub struct Update {
     pub game_time: f64,
     pub game_time_prev: f64,
     pub dt: f64,
+    /// The last `ClientMessage::SequencedInput` processed from the receiving client.
+    pub last_input_seq: u32,
+    /// Frame number of the update the entities are delta encoded against.
+    /// Entities not listed are unchanged since then.
+    /// None means everything is included.
+    pub baseline: Option<usize>,
     pub player_inputs: Vec<InputUpdate>,
     pub vehicles: Vec<VehicleUpdate>,
     pub projectiles: Vec<ProjectileUpdate>,
+    /// Entities in the baseline which no longer exist.
+    pub removed: RemovedEntities,
     pub debug_texts: Vec<String>,
     pub debug_texts_world: Vec<WorldText>,
     pub debug_shapes: Vec<DebugShape>,
-    pub server_timings: CommonTimings,
+    /// Only sent every `sv_net_timings_interval`.
+    pub server_timings: Option<CommonTimings>,
+}
+
+/// A compact view of the game for external bot controllers.
+///
+/// Controllers connect to `sv_net_controller_listen_addr`,
+/// send `ClientMessage::Version` (the server replies with `ServerMessage::Version` or `Rejected`),
+/// then `ClientMessage::Connect` to set their name
+/// and then answer each observation with `ClientMessage::Input`.
+/// Both directions use the same framing as normal clients -
+/// a little endian u32 length (including itself) followed by the bincode encoded message.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Observation {
+    pub frame_num: usize,
+    pub game_time: f64,
+    /// Only in the first observation, the map doesn't change.
+    pub map: Option<ObservedMap>,
+    /// The controlled vehicle. None if the player doesn't have one.
+    pub vehicle: Option<ObservedVehicle>,
+    pub cur_weapon: Weapon,
+    /// Whether each weapon can fire now, indexed by `Weapon as usize`.
+    pub weapons_ready: Vec<bool>,
+    /// Living enemies which are in line of sight.
+    pub enemies: Vec<ObservedVehicle>,
+    pub projectiles: Vec<ObservedProjectile>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ObservedMap {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, starting at the top left.
+    pub tiles: Vec<SurfaceKind>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ObservedVehicle {
+    pub index: u32,
+    pub veh_type: VehicleType,
+    pub physics: EntityPhysics,
+    /// Relative to the chassis.
+    pub turret_angle: f64,
+    pub hp_fraction: f64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+pub struct ObservedProjectile {
+    pub index: u32,
+    pub weapon: Weapon,
+    pub physics: EntityPhysics,
+    pub owner: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct InputUpdate {
     pub index: u32,
     pub net_input: NetInput,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Fields which are None are unchanged since the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct VehicleUpdate {
     pub index: u32,
-    pub physics: EntityPhysics,
-    pub turret_angle_current: f64,
-    pub turret_angle_wanted: f64,
+    pub physics: PhysicsUpdate,
+    pub turret_angle_current: Option<QuantizedAngle>,
+    pub turret_angle_wanted: Option<QuantizedAngle>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub struct ProjectileUpdate {
     pub index: u32,
-    pub physics: EntityPhysics,
+    pub physics: PhysicsUpdate,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -208,6 +419,42 @@ pub struct Kill {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
+pub struct MatchEnd {
+    /// Final scores so the client's scoreboard matches the server exactly.
+    pub scores: Vec<PlayerScore>,
+    pub team_scores: [TeamScore; 2],
+    /// Game time when the next match starts.
+    pub next_match_time: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerScore {
+    pub index: u32,
+    pub score: Score,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MatchStart {
+    pub time_limit: f64,
+    pub game_mode: GameMode,
+    /// Teams can change between matches, for example when switching game modes.
+    pub teams: Vec<PlayerTeam>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlayerTeam {
+    pub index: u32,
+    pub team: Option<Team>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ChatMessage {
+    /// Player index, None for messages from the server such as joins and leaves.
+    pub sender: Option<u32>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
 pub struct EntityPhysics {
     pub pos: Vec2f,
     pub vel: Vec2f,
@@ -322,4 +569,66 @@ mod tests {
             ]
         )
     }
+
+    #[test]
+    fn rejected_format() {
+        // Clients of any version must be able to read why they can't connect.
+
+        let serialized = net::serialize(ServerMessage::Rejected("No".to_owned()));
+        assert_eq!(
+            serialized.bytes,
+            [
+                0x12, 0x00, 0x00, 0x00, // total len
+                0x01, 0x00, 0x00, 0x00, // message variant
+                0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // reason len
+                0x4e, 0x6f, // "No"
+            ]
+        );
+    }
+
+    #[test]
+    fn version_compatibility() {
+        let server = Version::current();
+        assert!(Version::check_compatible(&server, &server).is_ok());
+
+        let mut client = Version::current();
+        client.patch += 1;
+        client.extra = None;
+        assert!(Version::check_compatible(&client, &server).is_ok());
+
+        let mut client = Version::current();
+        client.minor += 1;
+        assert!(Version::check_compatible(&client, &server).is_err());
+
+        let mut client = Version::current();
+        client.major += 1;
+        assert!(Version::check_compatible(&client, &server).is_err());
+
+        let mut client = Version::current();
+        client.game = "RustCycles".to_owned();
+        let reason = Version::check_compatible(&client, &server).unwrap_err();
+        assert!(reason.contains("RustCycles"));
+    }
+
+    #[test]
+    fn controller_input_format() {
+        // External bot controllers encode this by hand so it shouldn't change by accident.
+
+        let net_input = NetInput {
+            up: true,
+            fire: true,
+            ..NetInput::default()
+        };
+        let serialized = net::serialize(ClientMessage::Input(net_input));
+        assert_eq!(
+            serialized.bytes,
+            [
+                0x14, 0x00, 0x00, 0x00, // total len
+                0x02, 0x00, 0x00, 0x00, // message variant
+                0x00, 0x00, 0x01, 0x00, // left, right, up, down
+                0x00, 0x00, 0x00, 0x00, // turret_left, turret_right, prev_weapon, next_weapon
+                0x01, 0x00, 0x00, 0x00, // fire, mine, self_destruct, horn
+            ]
+        );
+    }
 }
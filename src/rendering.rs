@@ -78,11 +78,14 @@ impl Client {
         } = self;
 
         let player = &gs.players[local_player_handle];
-        let player_vehicle = &gs.vehicles[player.vehicle.unwrap()];
+        // Observers and players who haven't received their vehicle yet don't have one.
+        let player_vehicle = player.vehicle.map(|handle| &gs.vehicles[handle]);
         let player_entity_pos = if let Some(gm_handle) = player.guided_missile {
             gs.projectiles[gm_handle].pos
-        } else {
+        } else if let Some(player_vehicle) = player_vehicle {
             player_vehicle.pos
+        } else {
+            cg.observer_pos
         };
 
         // Don't put the camera so close to the edge that it would render area outside the map.
@@ -160,6 +163,23 @@ impl Client {
                 || scr_pos.y > outside_view_bottom_right.y
         };
 
+        // Draw mines - below everything else so they're easy to miss
+        // LATER Use a texture
+        for (_, mine) in &gs.mines {
+            let scr_pos = mine.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+            let (x, y) = (scr_pos.x as f32, scr_pos.y as f32);
+            draw_circle(x, y, 5.0, DARKGRAY);
+            let light = if gs.game_time >= mine.arm_time {
+                RED
+            } else {
+                GRAY
+            };
+            draw_circle(x, y, 2.0, light);
+        }
+
         // Draw MGs
         for (_, mg) in weapon_projectiles(Weapon::Mg) {
             let scr_pos = mg.pos + camera_offset;
@@ -274,7 +294,43 @@ impl Client {
             // }
         }
 
-        // LATER Draw cow
+        // Draw cow
+        // LATER Use a texture
+        if let Some(cow) = &gs.cow {
+            let carrier_vehicle = cow
+                .carrier
+                .and_then(|carrier| gs.players.get(carrier).and_then(|p| p.vehicle))
+                .and_then(|vehicle_handle| gs.vehicles.get(vehicle_handle));
+            let (pos, angle) = match carrier_vehicle {
+                Some(vehicle) => (vehicle.pos, vehicle.angle),
+                None => (cow.pos, 0.0),
+            };
+            let scr_pos = pos + camera_offset;
+            if !cull(scr_pos) {
+                let s = cvars.r_cow_scale;
+                let x = scr_pos.x as f32;
+                let y = scr_pos.y as f32;
+                let rot = angle as f32;
+                let dir = Vec2::new(rot.cos(), rot.sin());
+                let side = Vec2::new(-dir.y, dir.x);
+                let point = |forward: f32, sideways: f32| {
+                    let p = dir * forward * s + side * sideways * s;
+                    (x + p.x, y + p.y)
+                };
+
+                draw_ellipse(x, y, 10.0 * s, 6.0 * s, rot.to_degrees(), WHITE);
+                let (hx, hy) = point(11.0, 0.0);
+                draw_circle(hx, hy, 4.0 * s, WHITE);
+                let (nx, ny) = point(14.0, 0.0);
+                draw_circle(nx, ny, 2.0 * s, PINK);
+                for (forward, sideways, radius) in
+                    [(-4.0, -2.0, 2.5), (3.0, 2.5, 2.0), (-6.0, 3.0, 1.5)]
+                {
+                    let (sx, sy) = point(forward, sideways);
+                    draw_circle(sx, sy, radius * s, BLACK);
+                }
+            }
+        }
 
         // Draw turrets
         for (_, vehicle) in &gs.vehicles {
@@ -300,6 +356,35 @@ impl Client {
             );
         }
 
+        // Draw spawn protection shields
+        for (_, vehicle) in &gs.vehicles {
+            if !vehicle.protected(gs.game_time) {
+                continue;
+            }
+
+            let scr_pos = vehicle.pos + camera_offset;
+            if cull(scr_pos) {
+                continue;
+            }
+
+            let radius = vehicle
+                .hitbox
+                .mins
+                .magnitude()
+                .max(vehicle.hitbox.maxs.magnitude()) as f32;
+            // Fade out so it's clear when protection is about to end.
+            let remaining = (vehicle.protection_end - gs.game_time) as f32;
+            let alpha = cvars.cl_spawn_protection_shield_alpha * remaining.min(1.0);
+            let color = Color::new(1.0, 1.0, 1.0, alpha);
+            draw_circle_lines(
+                scr_pos.x as f32,
+                scr_pos.y as f32,
+                radius,
+                cvars.cl_spawn_protection_shield_thickness,
+                color,
+            );
+        }
+
         // Draw explosions
         let iter: Box<dyn Iterator<Item = &Explosion>> = if cvars.r_explosions_reverse_order {
             Box::new(cg.explosions.iter().rev())
@@ -410,20 +495,28 @@ impl Client {
                     continue;
                 }
 
-                let name = &gs.players[vehicle.owner].name;
+                let owner = &gs.players[vehicle.owner];
+                let name = &owner.name;
                 let size = measure_text(name, None, cvars.hud_names_font_size as u16, 1.0);
+                let mut color = Color::new(
+                    cvars.hud_names_brightness as f32,
+                    cvars.hud_names_brightness as f32,
+                    cvars.hud_names_brightness as f32,
+                    cvars.hud_names_alpha as f32,
+                );
+                if let Some(team) = owner.team {
+                    let team_color = cvars.hud_team_color(team);
+                    color.r = team_color.x;
+                    color.g = team_color.y;
+                    color.b = team_color.z;
+                }
                 render_text_with_shadow(
                     cvars,
                     name,
                     scr_pos.x as f32 - size.width / 2.0,
                     (scr_pos.y + cvars.hud_names_y) as f32,
                     cvars.hud_names_font_size,
-                    Color::new(
-                        cvars.hud_names_brightness as f32,
-                        cvars.hud_names_brightness as f32,
-                        cvars.hud_names_brightness as f32,
-                        cvars.hud_names_alpha as f32,
-                    ),
+                    color,
                     cvars.hud_names_shadow_x,
                     cvars.hud_names_shadow_y,
                     cvars.hud_names_shadow_alpha,
@@ -456,67 +549,69 @@ impl Client {
             }
         }
 
-        // Spawn location indicator
-        let alive_time = gs.game_time - player_vehicle.spawn_time;
-        if alive_time < cvars.cl_spawn_indicator_duration {
-            let vehicle_scr_pos = player_vehicle.pos + camera_offset;
-
-            // Radius here is distance from the square's center to its side.
-            let max_radius = cvars.cl_spawn_indicator_square_side_begin / 2.0;
-            let min_radius = cvars.cl_spawn_indicator_square_side_end / 2.0;
-            let fraction_complete =
-                (alive_time / cvars.cl_spawn_indicator_animation_time).clamp(0.0, 1.0) as f32;
-            let radius = (max_radius - min_radius) * (1.0 - fraction_complete) + min_radius;
-
-            // Horizontal and vertical lines pointing at the vehicle.
-            draw_line(
-                0.0,
-                vehicle_scr_pos.y as f32,
-                vehicle_scr_pos.x as f32 - min_radius,
-                vehicle_scr_pos.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32 + min_radius,
-                vehicle_scr_pos.y as f32,
-                self.viewport_size.x as f32,
-                vehicle_scr_pos.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32,
-                0.0,
-                vehicle_scr_pos.x as f32,
-                vehicle_scr_pos.y as f32 - min_radius,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-            draw_line(
-                vehicle_scr_pos.x as f32,
-                vehicle_scr_pos.y as f32 + min_radius,
-                vehicle_scr_pos.x as f32,
-                self.viewport_size.y as f32,
-                cvars.cl_spawn_indicator_thickness,
-                GREEN,
-            );
-
-            // Square with the vehicle in the center - first shrinks, then blinks.
-            let period = cvars.cl_spawn_indicator_blinking_period;
-            let still_shrinking = alive_time < cvars.cl_spawn_indicator_animation_time; // Don't blink during the animation
-            let blinking_disabled = period == 0.0;
-            let visible = alive_time % period < period / 2.0;
-            if still_shrinking || blinking_disabled || visible {
-                // We have to use thickness*2 here: https://github.com/not-fl3/macroquad/issues/271
-                draw_rectangle_lines(
-                    vehicle_scr_pos.x as f32 - radius,
-                    vehicle_scr_pos.y as f32 - radius,
-                    radius * 2.0,
-                    radius * 2.0,
-                    cvars.cl_spawn_indicator_thickness * 2.0,
+        if let Some(player_vehicle) = player_vehicle {
+            // Spawn location indicator
+            let alive_time = gs.game_time - player_vehicle.spawn_time;
+            if alive_time < cvars.cl_spawn_indicator_duration {
+                let vehicle_scr_pos = player_vehicle.pos + camera_offset;
+
+                // Radius here is distance from the square's center to its side.
+                let max_radius = cvars.cl_spawn_indicator_square_side_begin / 2.0;
+                let min_radius = cvars.cl_spawn_indicator_square_side_end / 2.0;
+                let fraction_complete =
+                    (alive_time / cvars.cl_spawn_indicator_animation_time).clamp(0.0, 1.0) as f32;
+                let radius = (max_radius - min_radius) * (1.0 - fraction_complete) + min_radius;
+
+                // Horizontal and vertical lines pointing at the vehicle.
+                draw_line(
+                    0.0,
+                    vehicle_scr_pos.y as f32,
+                    vehicle_scr_pos.x as f32 - min_radius,
+                    vehicle_scr_pos.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
                     GREEN,
                 );
+                draw_line(
+                    vehicle_scr_pos.x as f32 + min_radius,
+                    vehicle_scr_pos.y as f32,
+                    self.viewport_size.x as f32,
+                    vehicle_scr_pos.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
+                    GREEN,
+                );
+                draw_line(
+                    vehicle_scr_pos.x as f32,
+                    0.0,
+                    vehicle_scr_pos.x as f32,
+                    vehicle_scr_pos.y as f32 - min_radius,
+                    cvars.cl_spawn_indicator_thickness,
+                    GREEN,
+                );
+                draw_line(
+                    vehicle_scr_pos.x as f32,
+                    vehicle_scr_pos.y as f32 + min_radius,
+                    vehicle_scr_pos.x as f32,
+                    self.viewport_size.y as f32,
+                    cvars.cl_spawn_indicator_thickness,
+                    GREEN,
+                );
+
+                // Square with the vehicle in the center - first shrinks, then blinks.
+                let period = cvars.cl_spawn_indicator_blinking_period;
+                let still_shrinking = alive_time < cvars.cl_spawn_indicator_animation_time; // Don't blink during the animation
+                let blinking_disabled = period == 0.0;
+                let visible = alive_time % period < period / 2.0;
+                if still_shrinking || blinking_disabled || visible {
+                    // We have to use thickness*2 here: https://github.com/not-fl3/macroquad/issues/271
+                    draw_rectangle_lines(
+                        vehicle_scr_pos.x as f32 - radius,
+                        vehicle_scr_pos.y as f32 - radius,
+                        radius * 2.0,
+                        radius * 2.0,
+                        cvars.cl_spawn_indicator_thickness * 2.0,
+                        GREEN,
+                    );
+                }
             }
         }
 
@@ -548,170 +643,187 @@ impl Client {
         let mut player_points: Vec<_> = gs
             .players
             .iter()
+            .filter(|(_, player)| player.state == PlayerState::Playing)
             .map(|(index, player)| (index, player.score.points(cvars)))
             .collect();
         player_points.sort_by_key(|&(_, points)| Reverse(points));
 
-        // Score
-        let score_pos = hud_pos(view_pos, view_size, cvars.hud_score_x, cvars.hud_score_y);
-        let points = player.score.points(cvars).to_string();
-        render_text_with_shadow(
-            cvars,
-            &points,
-            score_pos.x,
-            score_pos.y,
-            cvars.hud_score_font_size,
-            WHITE,
-            cvars.hud_score_shadow_x,
-            cvars.hud_score_shadow_y,
-            1.0,
-        );
+        if let Some(player_vehicle) = player_vehicle {
+            // Score
+            let score_pos = hud_pos(view_pos, view_size, cvars.hud_score_x, cvars.hud_score_y);
+            let points = player.score.points(cvars).to_string();
+            render_text_with_shadow(
+                cvars,
+                &points,
+                score_pos.x,
+                score_pos.y,
+                cvars.hud_score_font_size,
+                WHITE,
+                cvars.hud_score_shadow_x,
+                cvars.hud_score_shadow_y,
+                1.0,
+            );
 
-        // Ranking
-        // Original RW shows "current rank / total players (+/- points difference to leader or second)"
-        // as a big but not bold number with a 1px shadow. E.g. "1/3 (+5)" or "2/3 (0)".
-        // There's no special treatement for players with the same number of points.
-        let ranking_pos = hud_pos(
-            view_pos,
-            view_size,
-            cvars.hud_ranking_x,
-            cvars.hud_ranking_y,
-        );
-        let current_index = player_points
-            .iter()
-            .position(|&(handle, _)| handle == local_player_handle)
-            .unwrap();
-        let points_diff = if current_index == 0 {
-            if player_points.len() == 1 {
-                // The player is alone.
-                0
+            // Ranking
+            // Original RW shows "current rank / total players (+/- points difference to leader or second)"
+            // as a big but not bold number with a 1px shadow. E.g. "1/3 (+5)" or "2/3 (0)".
+            // There's no special treatement for players with the same number of points.
+            let ranking_pos = hud_pos(
+                view_pos,
+                view_size,
+                cvars.hud_ranking_x,
+                cvars.hud_ranking_y,
+            );
+            let current_index = player_points
+                .iter()
+                .position(|&(handle, _)| handle == local_player_handle)
+                .unwrap();
+            let points_diff = if current_index == 0 {
+                if player_points.len() == 1 {
+                    // The player is alone.
+                    0
+                } else {
+                    player_points[current_index].1 - player_points[1].1
+                }
             } else {
-                player_points[current_index].1 - player_points[1].1
-            }
-        } else {
-            player_points[current_index].1 - player_points[0].1
-        };
-        let ranking = if points_diff > 0 {
-            // Only show the + sign for positive numbers, not 0
-            format!(
-                "{}/{} (+{})",
-                current_index + 1,
-                player_points.len(),
-                points_diff
-            )
-        } else {
-            format!(
-                "{}/{} ({})",
-                current_index + 1,
-                player_points.len(),
-                points_diff
-            )
-        };
-        render_text_with_shadow(
-            cvars,
-            &ranking,
-            ranking_pos.x,
-            ranking_pos.y,
-            cvars.hud_ranking_font_size,
-            WHITE,
-            cvars.hud_ranking_shadow_x,
-            cvars.hud_ranking_shadow_y,
-            1.0,
-        );
-
-        // Hit points (goes from green to red)
-        // Might wanna use https://crates.io/crates/colorsys if I need more color operations.
-        // Hit points to color (poor man's HSV):
-        // 0.0 = red
-        // 0.0..0.5 -> increase green channel
-        // 0.5 = yellow
-        // 0.5..1.0 -> decrease red channel
-        // 1.0 = green
-        let player_vehicle = &gs.vehicles[player.vehicle.unwrap()];
-        let r = 1.0 - (player_vehicle.hp_fraction.clamped(0.5, 1.0) - 0.5) * 2.0;
-        let g = player_vehicle.hp_fraction.clamped(0.0, 0.5) * 2.0;
-        let rgb = Color::new(r as f32, g as f32, 0.0, 1.0);
-        let hp_pos = hud_pos(view_pos, view_size, cvars.hud_hp_x, cvars.hud_hp_y);
-        draw_rectangle(
-            hp_pos.x,
-            hp_pos.y,
-            (cvars.hud_hp_width * player_vehicle.hp_fraction) as f32,
-            cvars.hud_hp_height as f32,
-            rgb,
-        );
-        if cvars.d_draw_texts && cvars.d_draw_hud {
-            let hp_number =
-                player_vehicle.hp_fraction * cvars.g_vehicle_hp(player_vehicle.veh_type);
-            let hp_text = format!("{}", hp_number);
+                player_points[current_index].1 - player_points[0].1
+            };
+            let ranking = if points_diff > 0 {
+                // Only show the + sign for positive numbers, not 0
+                format!(
+                    "{}/{} (+{})",
+                    current_index + 1,
+                    player_points.len(),
+                    points_diff
+                )
+            } else {
+                format!(
+                    "{}/{} ({})",
+                    current_index + 1,
+                    player_points.len(),
+                    points_diff
+                )
+            };
             render_text_with_shadow(
                 cvars,
-                &hp_text,
-                hp_pos.x - 25.0,
-                hp_pos.y + cvars.hud_hp_height as f32,
-                16.0,
-                RED,
-                1.0,
+                &ranking,
+                ranking_pos.x,
+                ranking_pos.y,
+                cvars.hud_ranking_font_size,
+                WHITE,
+                cvars.hud_ranking_shadow_x,
+                cvars.hud_ranking_shadow_y,
                 1.0,
-                cvars.d_draw_text_shadow_alpha,
             );
-        }
 
-        // Ammo
-        let ammo = player_vehicle.ammos[player.cur_weapon as usize];
-        let ammo_fraction = match ammo {
-            Ammo::Loaded(_ready_time, count) => {
-                let max = cvars.g_weapon_reload_ammo(player.cur_weapon);
-                count as f64 / max as f64
-            }
-            Ammo::Reloading(start, end) => {
-                let max_diff = end - start;
-                let cur_diff = gs.game_time - start;
-                cur_diff / max_diff
+            // Hit points (goes from green to red)
+            // Might wanna use https://crates.io/crates/colorsys if I need more color operations.
+            // Hit points to color (poor man's HSV):
+            // 0.0 = red
+            // 0.0..0.5 -> increase green channel
+            // 0.5 = yellow
+            // 0.5..1.0 -> decrease red channel
+            // 1.0 = green
+            let r = 1.0 - (player_vehicle.hp_fraction.clamped(0.5, 1.0) - 0.5) * 2.0;
+            let g = player_vehicle.hp_fraction.clamped(0.0, 0.5) * 2.0;
+            let rgb = Color::new(r as f32, g as f32, 0.0, 1.0);
+            let hp_pos = hud_pos(view_pos, view_size, cvars.hud_hp_x, cvars.hud_hp_y);
+            draw_rectangle(
+                hp_pos.x,
+                hp_pos.y,
+                (cvars.hud_hp_width * player_vehicle.hp_fraction) as f32,
+                cvars.hud_hp_height as f32,
+                rgb,
+            );
+            if cvars.d_draw_texts && cvars.d_draw_hud {
+                let hp_number =
+                    player_vehicle.hp_fraction * cvars.g_vehicle_hp(player_vehicle.veh_type);
+                let hp_text = format!("{}", hp_number);
+                render_text_with_shadow(
+                    cvars,
+                    &hp_text,
+                    hp_pos.x - 25.0,
+                    hp_pos.y + cvars.hud_hp_height as f32,
+                    16.0,
+                    RED,
+                    1.0,
+                    1.0,
+                    cvars.d_draw_text_shadow_alpha,
+                );
             }
-        };
-        let ammo_pos = hud_pos(view_pos, view_size, cvars.hud_ammo_x, cvars.hud_ammo_y);
-        draw_rectangle(
-            ammo_pos.x,
-            ammo_pos.y,
-            (cvars.hud_ammo_width * ammo_fraction) as f32,
-            cvars.hud_ammo_height as f32,
-            YELLOW,
-        );
-        if cvars.d_draw_texts && cvars.d_draw_hud {
-            let ammo_number = match ammo {
-                Ammo::Loaded(_ready_time, count) => count,
-                Ammo::Reloading(_start, _end) => 0,
+
+            // Ammo
+            let ammo = player_vehicle.ammos[player.cur_weapon as usize];
+            let ammo_fraction = match ammo {
+                Ammo::Loaded(_ready_time, count) => {
+                    let max = cvars.g_weapon_reload_ammo(player.cur_weapon);
+                    count as f64 / max as f64
+                }
+                Ammo::Reloading(start, end) => {
+                    let max_diff = end - start;
+                    let cur_diff = gs.game_time - start;
+                    cur_diff / max_diff
+                }
             };
+            let ammo_pos = hud_pos(view_pos, view_size, cvars.hud_ammo_x, cvars.hud_ammo_y);
+            draw_rectangle(
+                ammo_pos.x,
+                ammo_pos.y,
+                (cvars.hud_ammo_width * ammo_fraction) as f32,
+                cvars.hud_ammo_height as f32,
+                YELLOW,
+            );
+            if cvars.d_draw_texts && cvars.d_draw_hud {
+                let ammo_number = match ammo {
+                    Ammo::Loaded(_ready_time, count) => count,
+                    Ammo::Reloading(_start, _end) => 0,
+                };
+                render_text_with_shadow(
+                    cvars,
+                    &ammo_number.to_string(),
+                    ammo_pos.x - 25.0,
+                    ammo_pos.y + cvars.hud_ammo_height as f32,
+                    16.0,
+                    RED,
+                    1.0,
+                    1.0,
+                    cvars.d_draw_text_shadow_alpha,
+                );
+            }
+
+            // Weapon icon
+            // The original shadows were part of the image but this is good enough for now.
+            let weap_img = &assets.texs_weapon_icons[player.cur_weapon as usize];
+            let weap_icon_pos = hud_pos(
+                view_pos,
+                view_size,
+                cvars.hud_weapon_icon_x,
+                cvars.hud_weapon_icon_y,
+            ) - Vec2::new(weap_img.width(), weap_img.height()) / 2.0;
+            draw_texture(
+                weap_img,
+                weap_icon_pos.x + cvars.hud_weapon_icon_shadow_x,
+                weap_icon_pos.y + cvars.hud_weapon_icon_shadow_y,
+                Color::new(0.0, 0.0, 0.0, cvars.hud_weapon_icon_shadow_alpha as f32),
+            );
+            draw_texture(weap_img, weap_icon_pos.x, weap_icon_pos.y, WHITE);
+        } else if player.state == PlayerState::Observing {
+            let text = "Observing - press O to join";
+            let size = measure_text(text, None, cvars.hud_observing_font_size as u16, 1.0);
+            let pos = hud_pos(view_pos, view_size, 0.0, cvars.hud_observing_y);
             render_text_with_shadow(
                 cvars,
-                &ammo_number.to_string(),
-                ammo_pos.x - 25.0,
-                ammo_pos.y + cvars.hud_ammo_height as f32,
-                16.0,
-                RED,
-                1.0,
+                text,
+                pos.x + (view_size.x as f32 - size.width) / 2.0,
+                pos.y,
+                cvars.hud_observing_font_size,
+                WHITE,
+                cvars.hud_observing_shadow_x,
+                cvars.hud_observing_shadow_y,
                 1.0,
-                cvars.d_draw_text_shadow_alpha,
             );
         }
 
-        // Weapon icon
-        // The original shadows were part of the image but this is good enough for now.
-        let weap_img = &assets.texs_weapon_icons[player.cur_weapon as usize];
-        let weap_icon_pos = hud_pos(
-            view_pos,
-            view_size,
-            cvars.hud_weapon_icon_x,
-            cvars.hud_weapon_icon_y,
-        ) - Vec2::new(weap_img.width(), weap_img.height()) / 2.0;
-        draw_texture(
-            weap_img,
-            weap_icon_pos.x + cvars.hud_weapon_icon_shadow_x,
-            weap_icon_pos.y + cvars.hud_weapon_icon_shadow_y,
-            Color::new(0.0, 0.0, 0.0, cvars.hud_weapon_icon_shadow_alpha as f32),
-        );
-        draw_texture(weap_img, weap_icon_pos.x, weap_icon_pos.y, WHITE);
-
         // Notifications
         let mut notification_y = if cvars.hud_notifications_y_from_center != 0.0 {
             screen_height() / 2.0 + cvars.hud_notifications_y_from_center
@@ -778,12 +890,28 @@ impl Client {
         }
 
         // Scoreboard
-        if player_vehicle.destroyed() {
+        let intermission_end = match gs.match_state {
+            MatchState::Playing => None,
+            MatchState::Intermission { end_time } => Some(end_time),
+        };
+        let dead = player_vehicle.is_some_and(|vehicle| vehicle.destroyed());
+        if dead || intermission_end.is_some() {
             let width = cvars.hud_scoreboard_width_name
                 + cvars.hud_scoreboard_width_kills
                 + cvars.hud_scoreboard_width_deaths
                 + cvars.hud_scoreboard_width_points;
-            let height = (gs.players.len() + 1) as f32 * cvars.hud_scoreboard_line_height as f32;
+            // Observers have no score, they're listed under the table.
+            let observers: Vec<_> = gs
+                .players
+                .iter()
+                .filter(|(_, player)| player.state == PlayerState::Observing)
+                .map(|(_, player)| player.name.as_str())
+                .collect();
+            let mut lines = player_points.len() + 1;
+            if !observers.is_empty() {
+                lines += 2;
+            }
+            let height = lines as f32 * cvars.hud_scoreboard_line_height as f32;
             let x_start = view_pos.x as f32 + (view_size.x as f32 - width) / 2.0;
             let mut x = x_start.floor();
             let mut y = view_pos.y as f32 + (view_size.y as f32 - height) / 2.0;
@@ -793,6 +921,29 @@ impl Client {
             let sx = cvars.hud_scoreboard_shadow_x;
             let sy = cvars.hud_scoreboard_shadow_y;
 
+            if let Some(end_time) = intermission_end {
+                let remaining = (end_time - gs.game_time).max(0.0).ceil();
+                let text = format!("Match over - next match in {remaining} s");
+                let header_y = y - cvars.hud_scoreboard_line_height as f32 * 2.0;
+                render_text_with_shadow(cvars, &text, x, header_y, fs, WHITE, sx, sy, 1.0);
+            }
+
+            if gs.game_mode.has_teams() {
+                // Team totals go to the right of the table so the table doesn't move.
+                let mut team_y = y;
+                for team in [Team::Red, Team::Blue] {
+                    let color = cvars.hud_team_color(team).into();
+                    let team_score = &gs.team_scores[team as usize];
+                    let text = match gs.game_mode {
+                        GameMode::Ctc(_) => format!("{team:?}: {} captures", team_score.captures),
+                        _ => format!("{team:?}: {} kills", team_score.kills),
+                    };
+                    let team_x = x + width + cvars.hud_scoreboard_width_points;
+                    render_text_with_shadow(cvars, &text, team_x, team_y, fs, color, sx, sy, 1.0);
+                    team_y += cvars.hud_scoreboard_line_height as f32;
+                }
+            }
+
             // LATER bold header
             render_text_with_shadow(cvars, "Name", x, y, fs, WHITE, sx, sy, 1.0);
             x += cvars.hud_scoreboard_width_name;
@@ -805,12 +956,17 @@ impl Client {
             y += cvars.hud_scoreboard_line_height as f32;
 
             for (player_handle, points) in player_points {
-                let color = if player_handle == local_player_handle {
-                    WHITE
+                let player = &gs.players[player_handle];
+                let mut color = if let Some(team) = player.team {
+                    cvars.hud_team_color(team).into()
                 } else {
-                    Color::new(0.8, 0.8, 0.8, 1.0)
+                    WHITE
                 };
-                let player = &gs.players[player_handle];
+                if player_handle != local_player_handle {
+                    color.r *= 0.8;
+                    color.g *= 0.8;
+                    color.b *= 0.8;
+                }
                 let name = &player.name;
                 let kills = &player.score.kills.to_string();
                 let deaths = &player.score.deaths.to_string();
@@ -827,6 +983,12 @@ impl Client {
 
                 y += cvars.hud_scoreboard_line_height as f32;
             }
+
+            if !observers.is_empty() {
+                y += cvars.hud_scoreboard_line_height as f32;
+                let text = format!("Observing: {}", observers.join(", "));
+                render_text_with_shadow(cvars, &text, x_start, y, fs, GRAY, sx, sy, 1.0);
+            }
         }
 
         // Clear background around the map if it's smaller than the screen.
@@ -922,6 +1084,42 @@ impl Client {
     fn render_shared(&self, cvars: &Cvars) {
         let screen_size = Vec2f::new(screen_width() as f64, screen_height() as f64);
 
+        // Chat - the line being typed at the bottom, newest messages above it
+        let mut chat_pos = hud_pos(
+            Vec2f::zero(),
+            screen_size,
+            cvars.hud_chat_x,
+            cvars.hud_chat_y,
+        );
+        if let Some(line) = &self.cg.chat_line {
+            render_text_with_shadow(
+                cvars,
+                &format!("say: {line}_"),
+                chat_pos.x,
+                chat_pos.y,
+                cvars.hud_chat_font_size,
+                cvars.hud_chat_color.into(),
+                cvars.hud_chat_shadow_x,
+                cvars.hud_chat_shadow_y,
+                cvars.hud_chat_shadow_alpha,
+            );
+            chat_pos.y -= cvars.hud_chat_line_height;
+        }
+        for line in self.cg.chat.iter().rev().take(cvars.hud_chat_lines_max) {
+            render_text_with_shadow(
+                cvars,
+                &line.text,
+                chat_pos.x,
+                chat_pos.y,
+                cvars.hud_chat_font_size,
+                line.color.into(),
+                cvars.hud_chat_shadow_x,
+                cvars.hud_chat_shadow_y,
+                cvars.hud_chat_shadow_alpha,
+            );
+            chat_pos.y -= cvars.hud_chat_line_height;
+        }
+
         // Draw FPS
         if cvars.d_fps {
             let fps_pos = hud_pos(Vec2f::zero(), screen_size, cvars.d_fps_x, cvars.d_fps_y);
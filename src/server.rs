@@ -2,12 +2,15 @@
 //!
 //! All data affecting gameplay, players, bots, networking...
 
-use std::{io::ErrorKind, mem, net::TcpListener};
+use std::{io::ErrorKind, mem};
 
 use crate::{
     debug::{self, DEBUG_SHAPES, DEBUG_TEXTS, DEBUG_TEXTS_WORLD},
+    delta::{Changes, DeltaState, QuantizedAngle, QuantizedPhysics, VehicleState},
+    nav::NavGraph,
     net::{self, Connection, Listener, NetworkMessage},
     prelude::*,
+    stats::MatchStats,
     BOT_NAMES,
 };
 
@@ -30,22 +33,53 @@ pub struct Server {
 }
 
 pub struct ServerGame {
-    pub listener: Box<dyn Listener<ClientMessage>>,
+    /// Listeners for human clients, one per transport.
+    pub listeners: Vec<Box<dyn Listener<ClientMessage>>>,
+    /// Listener for external bot controllers, if enabled.
+    pub controller_listener: Option<Box<dyn Listener<ClientMessage>>>,
+    /// Connections which haven't finished the version handshake yet.
+    pub pending: Vec<PendingConnection>,
     pub clients: Arena<RemoteClient>,
     /// Handles to remote clients that have disconnected.
     pub disconnected: FnvHashSet<Index>,
 
+    /// Walkable tiles for bot pathfinding, the map doesn't change during the server's lifetime.
+    pub nav: NavGraph,
+    /// Ordered from the easiest to the hardest.
+    pub bot_profiles: Vec<BotProfile>,
+
     pub paused: bool,
 
     /// The RNG for all gamelogic
     pub rng: Xoshiro256PlusPlus,
 
+    pub stats: MatchStats,
+
+    /// Game time when server timings are sent to clients next.
+    pub next_timings_time: f64,
+
+    /// Wall clock time in seconds, only used to measure performance and rate limit chat.
+    /// Not macroquad's `get_time` because headless simulations run without a window.
+    pub clock: fn() -> f64,
+
     pub update_fps: Fps,
     pub update_durations: Durations,
     pub gamelogic_fps: Fps,
     pub gamelogic_durations: Durations,
 }
 
+/// How long to keep a rejected connection open so the reason gets delivered.
+const REJECTED_LINGER: f64 = 1.0;
+
+pub struct PendingConnection {
+    conn: Box<dyn Connection<ClientMessage>>,
+    controller: bool,
+    /// Game time when the connection was accepted.
+    since: f64,
+    /// Rejected connections only wait until the reason is delivered.
+    rejected: bool,
+}
+
 #[derive(Debug)]
 pub enum SendDest {
     /// Index to RemoteClient
@@ -56,39 +90,134 @@ pub enum SendDest {
 pub struct RemoteClient {
     conn: Box<dyn Connection<ClientMessage>>,
     player_handle: Index,
+    /// External bot controllers only get `Observation`s.
+    controller: bool,
+    /// Controllers get the map only in their first observation.
+    map_sent: bool,
+    /// Whether the client sent `Connect`, joins and leaves are announced only after that.
+    connected: bool,
+    /// Wall clock times of recent chat messages for rate limiting.
+    chat_times: VecDeque<f64>,
+    /// Wall clock time when the player last started observing or joined.
+    state_change_time: f64,
+    /// Received inputs with their sequence numbers, one is applied each tick.
+    inputs: VecDeque<(u32, NetInput)>,
+    /// The last input applied, acknowledged in each `Update`
+    /// so the client knows which inputs to replay.
+    last_input_seq: u32,
+    /// The last `Update` the client acknowledged receiving.
+    acked_update: Option<usize>,
+    /// Recently sent states, the acknowledged one is the baseline for the next update.
+    sent_states: VecDeque<DeltaState>,
+    bandwidth: BandwidthStats,
+}
+
+/// Bytes sent to one client since `start_time`.
+#[derive(Debug)]
+struct BandwidthStats {
+    start_time: f64,
+    bytes: usize,
+    update_bytes: usize,
+    /// What the updates would take without delta encoding.
+    full_update_bytes: usize,
+}
+
+impl BandwidthStats {
+    fn new(start_time: f64) -> Self {
+        Self {
+            start_time,
+            bytes: 0,
+            update_bytes: 0,
+            full_update_bytes: 0,
+        }
+    }
 }
 
 impl RemoteClient {
-    fn new(conn: Box<dyn Connection<ClientMessage>>, player_handle: Index) -> Self {
+    fn new(
+        conn: Box<dyn Connection<ClientMessage>>,
+        player_handle: Index,
+        controller: bool,
+        game_time: f64,
+    ) -> Self {
         Self {
             conn,
             player_handle,
+            controller,
+            map_sent: false,
+            connected: false,
+            chat_times: VecDeque::new(),
+            state_change_time: f64::NEG_INFINITY,
+            inputs: VecDeque::new(),
+            last_input_seq: 0,
+            acked_update: None,
+            sent_states: VecDeque::new(),
+            bandwidth: BandwidthStats::new(game_time),
+        }
+    }
+
+    /// Rate limiting for chat messages and renames.
+    /// Uses wall clock time so chat keeps working while paused.
+    fn chat_allowed(&mut self, cvars: &Cvars, now: f64) -> bool {
+        let period = cvars.sv_chat_rate_period;
+        while let Some(&time) = self.chat_times.front() {
+            if time + period > now {
+                break;
+            }
+            self.chat_times.pop_front();
         }
+        if self.chat_times.len() >= cvars.sv_chat_rate_messages {
+            return false;
+        }
+        self.chat_times.push_back(now);
+        true
+    }
+
+    /// Rate limiting for switching between observing and playing.
+    fn state_change_allowed(&mut self, cvars: &Cvars, now: f64) -> bool {
+        if now - self.state_change_time < cvars.sv_observe_interval {
+            return false;
+        }
+        self.state_change_time = now;
+        true
     }
 }
 
 impl Server {
-    pub fn new(cvars: &Cvars, map: Map) -> Self {
-        let listener = TcpListener::bind(&cvars.sv_net_listen_addr).unwrap();
-        listener.set_nonblocking(true).unwrap();
-        dbg_logf!("Listening on {}", &cvars.sv_net_listen_addr);
-
+    pub fn new(
+        cvars: &Cvars,
+        map: Map,
+        bot_profiles: Vec<BotProfile>,
+        listeners: Vec<Box<dyn Listener<ClientMessage>>>,
+        controller_listener: Option<Box<dyn Listener<ClientMessage>>>,
+        clock: fn() -> f64,
+    ) -> Self {
         let sg = ServerGame {
-            listener: Box::new(listener),
+            listeners,
+            controller_listener,
+            pending: Vec::new(),
             clients: Arena::new(),
             disconnected: FnvHashSet::default(),
 
+            nav: NavGraph::new(&map),
+            bot_profiles,
+
             paused: false,
 
             rng: Xoshiro256PlusPlus::seed_from_u64(cvars.d_seed),
 
+            stats: MatchStats::default(),
+
+            next_timings_time: 0.0,
+            clock,
+
             update_fps: Fps::new(),
             update_durations: Durations::new(),
             gamelogic_fps: Fps::new(),
             gamelogic_durations: Durations::new(),
         };
 
-        Self {
+        let mut server = Self {
             map,
             gs: GameState::new(),
             sg,
@@ -97,7 +226,9 @@ impl Server {
             real_time: 0.0,
             real_time_prev: 0.0,
             real_time_delta: 0.0,
-        }
+        };
+        server.ctx(cvars).start_match();
+        server
     }
 
     pub fn ctx<'a>(&'a mut self, cvars: &'a Cvars) -> ServerFrameCtx<'a> {
@@ -111,7 +242,7 @@ impl Server {
         // https://medium.com/@tglaiel/how-to-make-your-game-run-at-60fps-24c61210fe75
 
         self.sg.update_fps.tick(cvars.d_fps_period, self.real_time);
-        let start = macroquad::time::get_time();
+        let start = (self.sg.clock)();
 
         // Update time tracking variables
         self.real_time_prev = self.real_time;
@@ -128,7 +259,7 @@ impl Server {
             self.gamelogic(cvars, dt_update);
         }
 
-        let end = macroquad::time::get_time();
+        let end = (self.sg.clock)();
         self.sg
             .update_durations
             .add(cvars.d_timing_samples, end - start);
@@ -169,7 +300,7 @@ impl Server {
 
     /// Run one frame of gamelogic.
     fn gamelogic_tick(&mut self, cvars: &Cvars, game_time: f64) {
-        let start = macroquad::time::get_time();
+        let start = (self.sg.clock)();
         self.sg
             .gamelogic_fps
             .tick(cvars.d_fps_period, self.real_time);
@@ -196,32 +327,44 @@ impl Server {
         let mut ctx = ServerFrameCtx::new(cvars, &self.map, &mut self.gs, &mut self.sg);
 
         ctx.sys_net_accept();
+        ctx.sys_net_handshake();
         ctx.sys_connect_bots();
         ctx.sys_net_receive();
+        ctx.sys_net_inputs();
         ctx.sys_net_disconnect();
         ctx.sys_ai();
 
-        ctx.sys_respawning();
+        // During intermission, the world is frozen and only networking runs.
+        if ctx.gs.match_state == MatchState::Playing {
+            ctx.sys_respawning();
 
-        ctx.sys_player_weapon();
+            ctx.sys_player_weapon();
 
-        ctx.sys_vehicle_logic();
+            ctx.sys_vehicle_logic();
 
-        // It's probably a good idea to shoot before movement so that when turning
-        // the shot angle corresponds to the vehicle angle the player saw last frame.
-        // LATER Before turret turning too.
-        ctx.sys_shooting();
+            // It's probably a good idea to shoot before movement so that when turning
+            // the shot angle corresponds to the vehicle angle the player saw last frame.
+            // LATER Before turret turning too.
+            ctx.sys_shooting();
 
-        ctx.sys_vehicle_movement();
+            ctx.sys_vehicle_movement();
+            ctx.sys_vehicle_collisions();
 
-        ctx.sys_hm_turning();
-        ctx.sys_gm_turning();
+            ctx.sys_mines();
 
-        ctx.sys_projectiles();
+            ctx.sys_hm_turning();
+            ctx.sys_gm_turning();
 
-        ctx.sys_projectiles_timeout();
+            ctx.sys_projectiles();
 
-        ctx.self_destruct();
+            ctx.sys_projectiles_timeout();
+
+            ctx.self_destruct();
+
+            ctx.sys_cow();
+        }
+
+        ctx.sys_match();
 
         ctx.sys_debug_examples(v!(125, 300));
 
@@ -237,7 +380,7 @@ impl Server {
             player.input_prev = player.input;
         }
 
-        let end = macroquad::time::get_time();
+        let end = (self.sg.clock)();
         self.sg
             .gamelogic_durations
             .add(cvars.d_timing_samples, end - start);
@@ -249,8 +392,12 @@ impl ServerFrameCtx<'_> {
     pub fn net_send_all(&mut self, msg: ServerMessage) {
         let net_msg = net::serialize(msg);
         for (client_handle, client) in self.sg.clients.iter_mut() {
+            if client.controller {
+                continue;
+            }
             Self::net_send(
                 &net_msg,
+                true,
                 client_handle,
                 client,
                 self.gs,
@@ -262,11 +409,12 @@ impl ServerFrameCtx<'_> {
     fn net_send_all_except(&mut self, msg: ServerMessage, except_client_handle: Index) {
         let net_msg = net::serialize(msg);
         for (client_handle, client) in self.sg.clients.iter_mut() {
-            if client_handle == except_client_handle {
+            if client_handle == except_client_handle || client.controller {
                 continue;
             }
             Self::net_send(
                 &net_msg,
+                true,
                 client_handle,
                 client,
                 self.gs,
@@ -280,6 +428,7 @@ impl ServerFrameCtx<'_> {
         let client = &mut self.sg.clients[client_handle];
         Self::net_send(
             &net_msg,
+            true,
             client_handle,
             client,
             self.gs,
@@ -287,14 +436,21 @@ impl ServerFrameCtx<'_> {
         );
     }
 
+    /// Unreliable messages might get lost or arrive out of order (depends on the transport).
     fn net_send(
         net_msg: &NetworkMessage,
+        reliable: bool,
         client_handle: Index,
         client: &mut RemoteClient,
         gs: &GameState,
         disconnected: &mut FnvHashSet<Index>,
     ) {
-        let res = client.conn.send(net_msg);
+        client.bandwidth.bytes += net_msg.bytes.len();
+        let res = if reliable {
+            client.conn.send(net_msg)
+        } else {
+            client.conn.send_unreliable(net_msg)
+        };
         if let Err(e) = res {
             let index = client_handle.slot();
             let name = &gs.players[client.player_handle].name;
@@ -303,64 +459,164 @@ impl ServerFrameCtx<'_> {
         }
     }
 
-    /// Accept human clients trying to connect.
+    /// Accept human clients and bot controllers trying to connect.
     fn sys_net_accept(&mut self) {
-        loop {
-            match self.sg.listener.accept_conn() {
-                Ok(conn) => {
-                    // LATER Need a better handshake
-                    // so the initial messages don't change between versions.
-                    // Should contain only version and newer decides if compatible?
-                    // Version format - major.minor.patch + string for forward compat?
-                    // Look how comfy gets version from git.
-
-                    // Create client and player.
-                    let addr = conn.addr();
-                    let client = RemoteClient::new(conn, Index::DANGLING);
-                    let client_handle = self.sg.clients.insert(client);
-                    let name = "unconnected".to_owned(); // TODO?
-                    let player = Player::new(name, ClientType::Remote(client_handle));
-                    let player_handle = self.gs.players.insert(player);
-                    self.sg.clients[client_handle].player_handle = player_handle;
-
-                    let index = client_handle.slot();
-                    dbg_logf!("Connection accepted: {addr} -> client #{index}");
-
-                    // Send init to new player (contains his index).
-                    // Has to be the first message after connecting.
-                    let init = self.build_init(player_handle);
-                    let msg = ServerMessage::Init(init);
-                    self.net_send_one(msg, client_handle);
-
-                    // Send new player to everyone except the new player
-                    let player_init = PlayerInit {
-                        index: player_handle.slot(),
-                        name: self.gs.players[player_handle].name.clone(),
-                        // Currently we don't need to send score here
-                        // because all fields are 0 but in the future
-                        // some gamemodes might have a non-zero starting score
-                        // (e.g. number of lives in survival modes).
-                        score: self.gs.players[player_handle].score.clone(),
-                    };
-                    let msg = ServerMessage::AddPlayer(player_init);
-                    self.net_send_all_except(msg, client_handle);
-
-                    // Create vehicle, send to everyone
-                    // LATER New players should spectate
-                    self.spawn_vehicle(player_handle, true);
-
-                    dbg_logf!("Client #{index} init sent");
+        for i in 0..self.sg.listeners.len() {
+            loop {
+                match self.sg.listeners[i].accept_conn() {
+                    Ok(conn) => self.add_pending(conn, false),
+                    Err(err) => match err.kind() {
+                        ErrorKind::WouldBlock => {
+                            break;
+                        }
+                        _ => panic!("network error (accept): {}", err),
+                    },
                 }
+            }
+        }
+
+        while let Some(listener) = &mut self.sg.controller_listener {
+            match listener.accept_conn() {
+                Ok(conn) => self.add_pending(conn, true),
                 Err(err) => match err.kind() {
                     ErrorKind::WouldBlock => {
                         break;
                     }
-                    _ => panic!("network error (accept): {}", err),
+                    _ => panic!("network error (accept controller): {}", err),
                 },
             }
         }
     }
 
+    fn add_pending(&mut self, conn: Box<dyn Connection<ClientMessage>>, controller: bool) {
+        dbg_logf!("Connection from {} waiting for version", conn.addr());
+        self.sg.pending.push(PendingConnection {
+            conn,
+            controller,
+            since: self.gs.game_time,
+            rejected: false,
+        });
+    }
+
+    /// Check the version of new connections before sending them any game data.
+    ///
+    /// The first message on every connection has to be `ClientMessage::Version`,
+    /// the server replies with its own version or with the reason it's rejecting the client.
+    fn sys_net_handshake(&mut self) {
+        let pending = mem::take(&mut self.sg.pending);
+        for mut pc in pending {
+            let age = self.gs.game_time - pc.since;
+            let addr = pc.conn.addr();
+
+            if pc.rejected {
+                // Keep reading so the other side doesn't see a reset before it reads the reason.
+                let (_, closed) = pc.conn.receive();
+                if !closed && age < REJECTED_LINGER {
+                    self.sg.pending.push(pc);
+                }
+                continue;
+            }
+
+            let (msg, closed) = pc.conn.receive_one();
+            let res = match msg {
+                Some(ClientMessage::Version(cl_version)) => {
+                    dbg_logf!("Connection from {addr} has version {cl_version} {cl_version:?}");
+                    Version::check_compatible(&cl_version, &Version::current())
+                }
+                Some(_) => Err(
+                    "The client didn't send its version first, it's probably too old".to_owned(),
+                ),
+                None if closed => {
+                    dbg_logf!("Connection from {addr} closed during handshake");
+                    continue;
+                }
+                None if age > self.cvars.sv_net_handshake_timeout => {
+                    dbg_logf!("Connection from {addr} timed out during handshake");
+                    continue;
+                }
+                None => {
+                    self.sg.pending.push(pc);
+                    continue;
+                }
+            };
+
+            let (msg, accepted) = match res {
+                Ok(()) => (ServerMessage::Version(Version::current()), true),
+                Err(reason) => {
+                    dbg_logf!("Rejecting connection from {addr}: {reason}");
+                    (ServerMessage::Rejected(reason), false)
+                }
+            };
+            let net_msg = net::serialize(msg);
+            if let Err(err) = pc.conn.send(&net_msg) {
+                dbg_logf!("Connection from {addr} failed during handshake: {err}");
+                continue;
+            }
+
+            if accepted {
+                self.accept_client(pc.conn, pc.controller);
+            } else {
+                pc.rejected = true;
+                pc.since = self.gs.game_time;
+                self.sg.pending.push(pc);
+            }
+        }
+    }
+
+    fn accept_client(&mut self, conn: Box<dyn Connection<ClientMessage>>, controller: bool) {
+        // Create client and player.
+        let addr = conn.addr();
+        let client = RemoteClient::new(conn, Index::DANGLING, controller, self.gs.game_time);
+        let client_handle = self.sg.clients.insert(client);
+        let name = "unconnected".to_owned(); // TODO?
+        let mut player = Player::new(name, ClientType::Remote(client_handle));
+        let observing = self.cvars.sv_observe_on_connect && !controller;
+        if observing {
+            player.state = PlayerState::Observing;
+        } else {
+            player.team = self.pick_team();
+        }
+        let player_handle = self.gs.players.insert(player);
+        self.sg.clients[client_handle].player_handle = player_handle;
+
+        let index = client_handle.slot();
+        if controller {
+            dbg_logf!("Controller connection accepted: {addr} -> client #{index}");
+        } else {
+            dbg_logf!("Connection accepted: {addr} -> client #{index}");
+
+            // Send init to new player (contains his index).
+            // Has to be the first message after connecting.
+            // Controllers get everything they need in observations.
+            let init = self.build_init(player_handle);
+            let msg = ServerMessage::Init(init);
+            self.net_send_one(msg, client_handle);
+        }
+
+        // Send new player to everyone except the new player
+        let player_init = PlayerInit {
+            index: player_handle.slot(),
+            name: self.gs.players[player_handle].name.clone(),
+            // Currently we don't need to send score here
+            // because all fields are 0 but in the future
+            // some gamemodes might have a non-zero starting score
+            // (e.g. number of lives in survival modes).
+            score: self.gs.players[player_handle].score.clone(),
+            team: self.gs.players[player_handle].team,
+            observing,
+        };
+        let msg = ServerMessage::AddPlayer(player_init);
+        self.net_send_all_except(msg, client_handle);
+
+        // Create vehicle, send to everyone.
+        // Players get theirs after `Connect` which contains their vehicle preference.
+        if !observing && controller {
+            self.spawn_vehicle(player_handle, true);
+        }
+
+        dbg_logf!("Client #{index} init sent");
+    }
+
     fn build_init(&self, player_handle: Index) -> Init {
         let players = self
             .gs
@@ -370,6 +626,8 @@ impl ServerFrameCtx<'_> {
                 index: handle.slot(),
                 name: player.name.clone(),
                 score: player.score.clone(),
+                team: player.team,
+                observing: player.state == PlayerState::Observing,
             })
             .collect();
 
@@ -389,6 +647,7 @@ impl ServerFrameCtx<'_> {
                 turret_angle_current: vehicle.turret_angle_current,
                 turret_angle_wanted: vehicle.turret_angle_wanted,
                 spawn_time: vehicle.spawn_time,
+                protection_end: vehicle.protection_end,
                 owner: vehicle.owner.slot(),
             })
             .collect();
@@ -411,24 +670,52 @@ impl ServerFrameCtx<'_> {
             })
             .collect();
 
+        let mines = self
+            .gs
+            .mines
+            .iter()
+            .map(|(handle, mine)| MineInit {
+                index: handle.slot(),
+                pos: mine.pos,
+                arm_time: mine.arm_time,
+                owner: mine.owner.slot(),
+            })
+            .collect();
+
         Init {
-            sv_version: env!("GIT_VERSION").to_owned(),
             map_path: self.map.path.clone(),
             frame_num: self.gs.frame_num,
             game_time: self.gs.game_time,
             game_time_prev: self.gs.game_time_prev,
             dt: self.gs.dt,
+            time_limit: self.gs.time_limit,
+            game_mode: self.gs.game_mode.clone(),
+            match_state: self.gs.match_state,
+            team_scores: self.gs.team_scores.clone(),
             players,
             local_player1_index: player_handle.slot(),
             local_player2_index: None, // LATER(splitscreen)
             vehicles,
             projectiles,
+            mines,
+            cow: self.gs.cow.as_ref().map(|cow| CowInit {
+                pos: cow.pos,
+                carrier: cow.carrier.map(|carrier| carrier.slot()),
+            }),
         }
     }
 
     /// Add bot clients if necessary.
     fn sys_connect_bots(&mut self) {
-        let humans = self.sg.clients.len();
+        // Observers don't take up a spot, bots fill it until they join.
+        let humans = self
+            .sg
+            .clients
+            .iter()
+            .filter(|(_, client)| {
+                self.gs.players[client.player_handle].state == PlayerState::Playing
+            })
+            .count();
         let bots_min = self.cvars.g_players_min.saturating_sub(humans);
         let bots_max = self.cvars.g_players_max.saturating_sub(humans);
 
@@ -489,49 +776,169 @@ impl ServerFrameCtx<'_> {
                     format!("Bot {}", i + 1)
                 };
 
-                let ai = Ai::new(Index::DANGLING);
+                let profile = self.pick_bot_profile();
+                let ai = Ai::new(Index::DANGLING, profile);
                 let ai_handle = self.gs.ais.insert(ai);
-                let player = Player::new(name, ClientType::Ai(ai_handle));
+                let mut player = Player::new(name, ClientType::Ai(ai_handle));
+                // Bots have their favorite vehicles too, some don't care.
+                player.vehicle_preference =
+                    VehiclePreference::from_repr(self.sg.rng.gen_range(0..4)).unwrap();
+                player.team = self.pick_team();
                 let player_handle = self.gs.players.insert(player);
                 self.gs.ais[ai_handle].player = player_handle;
 
-                // LATER Use spawns when bot AI actually works
-                self.spawn_vehicle(player_handle, false);
+                // Usually nobody is connected yet when bots are added
+                // but the number of bots can also change later.
+                let player = &self.gs.players[player_handle];
+                let player_init = PlayerInit {
+                    index: player_handle.slot(),
+                    name: player.name.clone(),
+                    score: player.score.clone(),
+                    team: player.team,
+                    observing: false,
+                };
+                let msg = ServerMessage::AddPlayer(player_init);
+                self.net_send_all(msg);
+
+                self.spawn_vehicle(player_handle, true);
 
                 let index = ai_handle.slot();
                 let name = &self.gs.players[player_handle].name;
-                dbg_logf!("Added bot @{index} {name:?}");
+                let profile = &self.gs.ais[ai_handle].profile.name;
+                dbg_logf!("Added bot @{index} {name:?} ({profile})");
             }
         }
     }
 
+    /// A random profile around `bots_difficulty` so bots in one match can have different skill.
+    fn pick_bot_profile(&mut self) -> BotProfile {
+        let profiles = &self.sg.bot_profiles;
+        if profiles.is_empty() {
+            return BotProfile::default();
+        }
+
+        let spread = self.cvars.bots_difficulty_spread;
+        let mut difficulty = self.cvars.bots_difficulty;
+        if spread > 0.0 {
+            difficulty += self.sg.rng.gen_range(-spread..=spread);
+        }
+        let max = (profiles.len() - 1) as f64;
+        let i = (difficulty.clamp(0.0, 1.0) * max).round() as usize;
+        profiles[i].clone()
+    }
+
     /// Receive input and commands from remote clients.
     fn sys_net_receive(&mut self) {
         let mut reply_msgs = Vec::new();
+        let mut direct_msgs = Vec::new();
+        // Player handle and whether to observe or join.
+        let mut state_changes = Vec::new();
+        let mut first_spawns = Vec::new();
         for (client_handle, client) in self.sg.clients.iter_mut() {
             let (msgs, closed) = client.conn.receive();
 
             for msg in msgs {
                 match msg {
-                    ClientMessage::Version(_) => todo!(),
+                    ClientMessage::Version(_) => {
+                        let index = client_handle.slot();
+                        dbg_logf!("WARNING: Client #{index} sent its version again, ignoring");
+                    }
                     ClientMessage::Connect(connect) => {
                         let Connect {
-                            cl_version,
                             name1,
                             name2,
+                            vehicle,
                         } = connect;
                         let index = client_handle.slot();
-                        dbg_logf!("Client #{index} connected: {} ", cl_version);
+                        dbg_logf!("Client #{index} connected");
                         dbg_logf!("name1: {:?}", name1);
                         dbg_logf!("name2: {:?}", name2);
 
-                        self.gs.players[client.player_handle].name = name1;
+                        let mut name1 = sanitize(&name1, self.cvars.sv_name_length_max);
+                        if name1.is_empty() {
+                            name1 = format!("Player {index}");
+                        }
+
+                        // Clients send it again when the player changes their name.
+                        let player = &mut self.gs.players[client.player_handle];
+                        player.vehicle_preference = vehicle;
+                        let text = if !client.connected {
+                            client.connected = true;
+                            if player.state == PlayerState::Playing && player.vehicle.is_none() {
+                                first_spawns.push(client.player_handle);
+                            }
+                            format!("{name1} joined")
+                        } else if player.name == name1 {
+                            continue;
+                        } else if client.chat_allowed(self.cvars, (self.sg.clock)()) {
+                            format!("{} is now known as {name1}", player.name)
+                        } else {
+                            dbg_logf!("Client #{index} {:?} is renaming too fast", player.name);
+                            let msg = ServerMessage::Chat(ChatMessage {
+                                sender: None,
+                                text: "You're changing your name too fast".to_owned(),
+                            });
+                            direct_msgs.push((client_handle, msg));
+                            continue;
+                        };
+                        player.name = name1.clone();
+                        reply_msgs.push(ServerMessage::RenamePlayer {
+                            index: client.player_handle.slot(),
+                            name: name1,
+                        });
+                        reply_msgs.push(ServerMessage::Chat(ChatMessage { sender: None, text }));
                     }
                     ClientMessage::Input(net_input) => {
                         let player = &mut self.gs.players[client.player_handle];
                         player.input = net_input;
                     }
-                    ClientMessage::Chat(_) => unimplemented!(), // LATER
+                    ClientMessage::SequencedInput {
+                        seq,
+                        net_input,
+                        last_update,
+                    } => {
+                        // Inputs can arrive out of order over UDP, older ones are obsolete.
+                        let newest = client.inputs.back().map_or(client.last_input_seq, |i| i.0);
+                        if seq <= newest {
+                            continue;
+                        }
+                        client.inputs.push_back((seq, net_input));
+                        // Don't let a client with a higher framerate fall further and further behind.
+                        while client.inputs.len() > self.cvars.sv_net_input_queue_max {
+                            client.inputs.pop_front();
+                        }
+                        client.acked_update = last_update;
+                    }
+                    ClientMessage::VehiclePreference(preference) => {
+                        let player = &mut self.gs.players[client.player_handle];
+                        player.vehicle_preference = preference;
+                    }
+                    ClientMessage::Chat(text) => {
+                        let index = client_handle.slot();
+                        let name = &self.gs.players[client.player_handle].name;
+
+                        let text = sanitize(&text, self.cvars.sv_chat_length_max);
+                        if text.is_empty() {
+                            continue;
+                        }
+
+                        if !client.chat_allowed(self.cvars, (self.sg.clock)()) {
+                            dbg_logf!("Client #{index} {name:?} is chatting too fast: {text}");
+                            let msg = ServerMessage::Chat(ChatMessage {
+                                sender: None,
+                                text: "You're sending messages too fast".to_owned(),
+                            });
+                            direct_msgs.push((client_handle, msg));
+                            continue;
+                        }
+
+                        dbg_logf!("Chat: {name}: {text}");
+                        let msg = ServerMessage::Chat(ChatMessage {
+                            sender: Some(client.player_handle.slot()),
+                            text,
+                        });
+                        reply_msgs.push(msg);
+                    }
                     ClientMessage::Pause => {
                         self.sg.paused = !self.sg.paused;
 
@@ -541,8 +948,29 @@ impl ServerFrameCtx<'_> {
                         let msg = ServerMessage::Paused(self.sg.paused);
                         reply_msgs.push(msg);
                     }
-                    ClientMessage::Join => unimplemented!(), // LATER
-                    ClientMessage::Observe => unimplemented!(), // LATER
+                    ClientMessage::Join | ClientMessage::Observe => {
+                        let observe = matches!(msg, ClientMessage::Observe);
+                        let state = self.gs.players[client.player_handle].state;
+                        let wanted = if observe {
+                            PlayerState::Observing
+                        } else {
+                            PlayerState::Playing
+                        };
+                        // Bot controllers need a vehicle to control.
+                        if state == wanted || (observe && client.controller) {
+                            continue;
+                        }
+                        if !client.state_change_allowed(self.cvars, (self.sg.clock)()) {
+                            let msg = ServerMessage::Chat(ChatMessage {
+                                sender: None,
+                                text: "You're switching between observing and playing too fast"
+                                    .to_owned(),
+                            });
+                            direct_msgs.push((client_handle, msg));
+                            continue;
+                        }
+                        state_changes.push((client.player_handle, observe));
+                    }
                 }
             }
 
@@ -556,84 +984,328 @@ impl ServerFrameCtx<'_> {
         for msg in reply_msgs {
             self.net_send_all(msg);
         }
+        for (client_handle, msg) in direct_msgs {
+            self.net_send_one(msg, client_handle);
+        }
+        for player_handle in first_spawns {
+            self.spawn_vehicle(player_handle, true);
+        }
+        for (player_handle, observe) in state_changes {
+            if observe {
+                self.observe(player_handle);
+            } else {
+                self.join(player_handle);
+            }
+        }
+    }
+
+    /// Take away the player's vehicle and let them fly around as a free camera.
+    fn observe(&mut self, player_handle: Index) {
+        if self.gs.players[player_handle].state == PlayerState::Observing {
+            return;
+        }
+
+        self.start_observing(player_handle);
+        let msg = ServerMessage::PlayerObserve {
+            index: player_handle.slot(),
+        };
+        self.net_send_all(msg);
+
+        let name = &self.gs.players[player_handle].name;
+        dbg_logf!("Player {name:?} is observing");
+        let msg = ServerMessage::Chat(ChatMessage {
+            sender: None,
+            text: format!("{name} is now observing"),
+        });
+        self.net_send_all(msg);
+    }
+
+    /// Put the observer into the game with a new vehicle.
+    ///
+    /// Players who died just before observing get it
+    /// only after the respawn delay, same as if they stayed.
+    fn join(&mut self, player_handle: Index) {
+        if self.gs.players[player_handle].state == PlayerState::Playing {
+            return;
+        }
+
+        let team = self.pick_team();
+        let player = &mut self.gs.players[player_handle];
+        player.state = PlayerState::Playing;
+        player.score = Score::default();
+        player.team = team;
+        let msg = ServerMessage::PlayerJoin {
+            index: player_handle.slot(),
+            team,
+        };
+        self.net_send_all(msg);
+        let player = &mut self.gs.players[player_handle];
+        if player.death_time + self.cvars.g_respawn_delay < self.gs.game_time {
+            self.spawn_vehicle(player_handle, true);
+        } else {
+            player.respawn = Respawn::Scheduled;
+        }
+
+        let name = &self.gs.players[player_handle].name;
+        dbg_logf!("Player {name:?} joined the game");
+        let msg = ServerMessage::Chat(ChatMessage {
+            sender: None,
+            text: format!("{name} joined the game"),
+        });
+        self.net_send_all(msg);
     }
 
     /// Send updates to all clients.
+    ///
+    /// Each client gets only what changed since the last update it acknowledged.
     fn sys_net_send_updates(&mut self) {
-        let player_inputs = self
-            .gs
-            .players
-            .iter()
-            .map(|(handle, player)| InputUpdate {
-                index: handle.slot(),
-                net_input: player.input,
-            })
-            .collect();
+        let mut state = DeltaState::new(self.gs.frame_num);
+        for (handle, player) in self.gs.players.iter() {
+            state.inputs.insert(handle.slot(), player.input);
+        }
+        for (handle, vehicle) in self.gs.vehicles.iter() {
+            let physics = EntityPhysics {
+                pos: vehicle.pos,
+                vel: vehicle.vel,
+                angle: vehicle.angle,
+                turn_rate: vehicle.turn_rate,
+            };
+            let vehicle = VehicleState {
+                physics: QuantizedPhysics::new(&physics),
+                turret_angle_current: QuantizedAngle::new(vehicle.turret_angle_current),
+                turret_angle_wanted: QuantizedAngle::new(vehicle.turret_angle_wanted),
+            };
+            state.vehicles.insert(handle.slot(), vehicle);
+        }
+        for (handle, projectile) in self.gs.projectiles.iter() {
+            let physics = EntityPhysics {
+                pos: projectile.pos,
+                vel: projectile.vel,
+                angle: projectile.angle,
+                turn_rate: projectile.turn_rate,
+            };
+            state
+                .projectiles
+                .insert(handle.slot(), QuantizedPhysics::new(&physics));
+        }
 
-        let vehicles = self
-            .gs
-            .vehicles
-            .iter()
-            .map(|(handle, vehicle)| VehicleUpdate {
-                index: handle.slot(),
-                physics: EntityPhysics {
-                    pos: vehicle.pos,
-                    vel: vehicle.vel,
-                    angle: vehicle.angle,
-                    turn_rate: vehicle.turn_rate,
-                },
-                turret_angle_current: vehicle.turret_angle_current,
-                turret_angle_wanted: vehicle.turret_angle_wanted,
+        // Send debug items, then clear everything on the server (not just expired)
+        // so it doesn't get sent again next frame.
+        let debug_texts = DEBUG_TEXTS.take();
+        let debug_texts_world = DEBUG_TEXTS_WORLD.take();
+        let debug_shapes = DEBUG_SHAPES.take();
+
+        let server_timings = if self.gs.game_time >= self.sg.next_timings_time {
+            self.sg.next_timings_time = self.gs.game_time + self.cvars.sv_net_timings_interval;
+            let update_stats = self.sg.update_durations.get_stats().unwrap_or_default();
+            let gamelogic_stats = self.sg.gamelogic_durations.get_stats().unwrap_or_default();
+            Some(CommonTimings {
+                update_durations_avg: update_stats.0,
+                update_durations_max: update_stats.1,
+                gamelogic_durations_avg: gamelogic_stats.0,
+                gamelogic_durations_max: gamelogic_stats.1,
+                update_fps: self.sg.update_fps.get_fps(),
+                gamelogic_fps: self.sg.gamelogic_fps.get_fps(),
             })
-            .collect();
+        } else {
+            None
+        };
+
+        let build_update = |last_input_seq, baseline, changes| {
+            let Changes {
+                inputs,
+                vehicles,
+                projectiles,
+                removed,
+            } = changes;
+            let update = Update {
+                frame_num: self.gs.frame_num,
+                game_time: self.gs.game_time,
+                game_time_prev: self.gs.game_time_prev,
+                dt: self.gs.dt,
+                last_input_seq,
+                baseline,
+                player_inputs: inputs,
+                vehicles,
+                projectiles,
+                removed,
+                debug_texts: debug_texts.clone(),
+                debug_texts_world: debug_texts_world.clone(),
+                debug_shapes: debug_shapes.clone(),
+                server_timings,
+            };
+            net::serialize(ServerMessage::Update(update))
+        };
+
+        // What it would cost without delta encoding, only to show the savings.
+        let stats_enabled = self.cvars.sv_net_stats_interval > 0.0;
+        let full_len = if stats_enabled {
+            build_update(0, None, state.changed_since(None)).bytes.len()
+        } else {
+            0
+        };
+
+        for (client_handle, client) in self.sg.clients.iter_mut() {
+            if client.controller {
+                continue;
+            }
+
+            let baseline = if self.cvars.sv_net_delta {
+                client.acked_update.and_then(|frame_num| {
+                    client
+                        .sent_states
+                        .iter()
+                        .find(|sent| sent.frame_num == frame_num)
+                })
+            } else {
+                None
+            };
+            let changes = state.changed_since(baseline);
+            let baseline = baseline.map(|sent| sent.frame_num);
+            let net_msg = build_update(client.last_input_seq, baseline, changes);
+
+            client.bandwidth.update_bytes += net_msg.bytes.len();
+            client.bandwidth.full_update_bytes += full_len;
+            Self::net_send(
+                &net_msg,
+                false,
+                client_handle,
+                client,
+                self.gs,
+                &mut self.sg.disconnected,
+            );
+
+            // Acks only ever increase so older states will never be used again.
+            if let Some(acked) = client.acked_update {
+                client.sent_states.retain(|sent| sent.frame_num >= acked);
+            }
+            client.sent_states.push_back(state.clone());
+            while client.sent_states.len() > self.cvars.sv_net_delta_history {
+                client.sent_states.pop_front();
+            }
+
+            let elapsed = self.gs.game_time - client.bandwidth.start_time;
+            if stats_enabled && elapsed >= self.cvars.sv_net_stats_interval {
+                let index = client_handle.slot();
+                let name = &self.gs.players[client.player_handle].name;
+                let stats = &client.bandwidth;
+                dbg_logf!(
+                    "Client #{index} {name:?}: {:.1} kB/s total, updates {:.1} kB/s ({:.0}% of full updates)",
+                    stats.bytes as f64 / elapsed / 1000.0,
+                    stats.update_bytes as f64 / elapsed / 1000.0,
+                    stats.update_bytes as f64 / stats.full_update_bytes as f64 * 100.0,
+                );
+                client.bandwidth = BandwidthStats::new(self.gs.game_time);
+            }
+        }
+
+        self.send_observations();
+    }
+
+    /// Send each external bot controller what its player can see.
+    fn send_observations(&mut self) {
+        for client_handle in self.sg.clients.collect_handles() {
+            let client = &self.sg.clients[client_handle];
+            if !client.controller {
+                continue;
+            }
+            let observation = self.build_observation(client.player_handle, !client.map_sent);
+            self.sg.clients[client_handle].map_sent = true;
+            let msg = ServerMessage::Observation(observation);
+            self.net_send_one(msg, client_handle);
+        }
+    }
+
+    fn build_observation(&self, player_handle: Index, with_map: bool) -> Observation {
+        let map = with_map.then(|| {
+            let size = self.map.size();
+            let mut tiles = Vec::with_capacity(size.x * size.y);
+            for r in 0..size.y {
+                for c in 0..size.x {
+                    tiles.push(self.map.surface_at_index(Vec2u::new(c, r)).kind);
+                }
+            }
+            ObservedMap {
+                width: size.x as u32,
+                height: size.y as u32,
+                tiles,
+            }
+        });
+
+        let observe_vehicle = |handle: Index, vehicle: &Vehicle| ObservedVehicle {
+            index: handle.slot(),
+            veh_type: vehicle.veh_type,
+            physics: EntityPhysics {
+                pos: vehicle.pos,
+                vel: vehicle.vel,
+                angle: vehicle.angle,
+                turn_rate: vehicle.turn_rate,
+            },
+            turret_angle: vehicle.turret_angle_current,
+            hp_fraction: vehicle.hp_fraction,
+        };
+
+        let player = &self.gs.players[player_handle];
+        let mut vehicle = None;
+        let mut weapons_ready = vec![false; Weapon::COUNT];
+        let mut enemies = Vec::new();
+        if let Some(vehicle_handle) = player.vehicle {
+            let own = &self.gs.vehicles[vehicle_handle];
+            vehicle = Some(observe_vehicle(vehicle_handle, own));
+            for (ready, ammo) in weapons_ready.iter_mut().zip(&own.ammos) {
+                *ready = match *ammo {
+                    Ammo::Loaded(ready_time, _) => self.gs.game_time >= ready_time,
+                    Ammo::Reloading(..) => false,
+                };
+            }
+            enemies = self
+                .gs
+                .vehicles
+                .iter()
+                .filter(|&(handle, _)| self.sees_enemy(player_handle, vehicle_handle, handle))
+                .map(|(handle, enemy)| observe_vehicle(handle, enemy))
+                .collect();
+        }
 
         let projectiles = self
             .gs
             .projectiles
             .iter()
-            .map(|(handle, projectile)| ProjectileUpdate {
+            .map(|(handle, projectile)| ObservedProjectile {
                 index: handle.slot(),
+                weapon: projectile.weapon,
                 physics: EntityPhysics {
                     pos: projectile.pos,
                     vel: projectile.vel,
                     angle: projectile.angle,
                     turn_rate: projectile.turn_rate,
                 },
+                owner: projectile.owner.slot(),
             })
             .collect();
 
-        // Send debug items, then clear everything on the server (not just expired)
-        // so it doesn't get sent again next frame.
-        let debug_texts = DEBUG_TEXTS.take();
-        let debug_texts_world = DEBUG_TEXTS_WORLD.take();
-        let debug_shapes = DEBUG_SHAPES.take();
-
-        let update_stats = self.sg.update_durations.get_stats().unwrap_or_default();
-        let gamelogic_stats = self.sg.gamelogic_durations.get_stats().unwrap_or_default();
-        let server_timings = CommonTimings {
-            update_durations_avg: update_stats.0,
-            update_durations_max: update_stats.1,
-            gamelogic_durations_avg: gamelogic_stats.0,
-            gamelogic_durations_max: gamelogic_stats.1,
-            update_fps: self.sg.update_fps.get_fps(),
-            gamelogic_fps: self.sg.gamelogic_fps.get_fps(),
-        };
-
-        let update = Update {
+        Observation {
             frame_num: self.gs.frame_num,
             game_time: self.gs.game_time,
-            game_time_prev: self.gs.game_time_prev,
-            dt: self.gs.dt,
-            player_inputs,
-            vehicles,
+            map,
+            vehicle,
+            cur_weapon: player.cur_weapon,
+            weapons_ready,
+            enemies,
             projectiles,
-            debug_texts,
-            debug_texts_world,
-            debug_shapes,
-            server_timings,
-        };
-        let msg = ServerMessage::Update(update);
-        self.net_send_all(msg);
+        }
+    }
+
+    /// Apply one queued input per client so the client's prediction sees the same inputs.
+    ///
+    /// If none arrived since the last tick, the player keeps the previous input.
+    fn sys_net_inputs(&mut self) {
+        for (_, client) in self.sg.clients.iter_mut() {
+            if let Some((seq, net_input)) = client.inputs.pop_front() {
+                self.gs.players[client.player_handle].input = net_input;
+                client.last_input_seq = seq;
+            }
+        }
     }
 
     /// Remove data of disconnected clients, notify others.
@@ -641,6 +1313,7 @@ impl ServerFrameCtx<'_> {
         let handles = mem::take(&mut self.sg.disconnected); // Borrowck
         for client_handle in handles {
             let player_handle = self.sg.clients[client_handle].player_handle;
+            let connected = self.sg.clients[client_handle].connected;
             let name = self.gs.players[player_handle].name.clone();
             self.remove_player(player_handle);
 
@@ -650,9 +1323,173 @@ impl ServerFrameCtx<'_> {
                 index: player_handle.slot(),
             };
             self.net_send_all(msg);
+            if connected {
+                let msg = ServerMessage::Chat(ChatMessage {
+                    sender: None,
+                    text: format!("{name} left"),
+                });
+                self.net_send_all(msg);
+            }
 
             let index = client_handle.slot();
             dbg_logf!("Client #{index} {name:?} disconnected");
         }
     }
 }
+
+/// Remove control characters which could mess up rendering and logs, limit length.
+fn sanitize(text: &str, length_max: usize) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(length_max)
+        .collect();
+    text.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        net::TcpListener,
+        sync::OnceLock,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{map, net::TcpConnection, net_messages::Connect, sys_ai};
+
+    use super::*;
+
+    fn clock() -> f64 {
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64()
+    }
+
+    /// Run the server for a while and collect what the client received.
+    fn run(
+        cvars: &Cvars,
+        server: &mut Server,
+        conn: &mut dyn Connection<ServerMessage>,
+    ) -> Vec<ServerMessage> {
+        let mut received = Vec::new();
+        for _ in 0..20 {
+            server.update(cvars, server.real_time + 1.0 / 60.0);
+            thread::sleep(Duration::from_millis(1));
+            let (msgs, closed) = conn.receive();
+            assert!(!closed);
+            received.extend(msgs);
+        }
+        received
+    }
+
+    /// Start a server and connect a client which already sent its version.
+    fn start(cvars: &Cvars) -> (Server, TcpConnection) {
+        let texture_list = fs::read_to_string("data/texture_list.txt").unwrap();
+        let map_text = fs::read_to_string("maps/Corners (4).map").unwrap();
+        let map = map::parse_map(&map_text, map::parse_texture_list(&texture_list), "");
+        let bot_profiles = fs::read_to_string("data/bot_profiles.txt").unwrap();
+        let bot_profiles = sys_ai::parse_bot_profiles(&bot_profiles);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let listeners: Vec<Box<dyn Listener<ClientMessage>>> = vec![Box::new(listener)];
+        let server = Server::new(cvars, map, bot_profiles, listeners, None, clock);
+
+        let mut conn = net::tcp_connect_blocking(cvars, &addr);
+        let msg = ClientMessage::Version(Version::current());
+        Connection::<ServerMessage>::send(&mut conn, &net::serialize(msg)).unwrap();
+        (server, conn)
+    }
+
+    fn connect(conn: &mut TcpConnection, name: &str) {
+        let connect = Connect {
+            name1: name.to_owned(),
+            name2: None,
+            vehicle: VehiclePreference::Hovercraft,
+        };
+        let msg = ClientMessage::Connect(connect);
+        Connection::<ServerMessage>::send(conn, &net::serialize(msg)).unwrap();
+    }
+
+    #[test]
+    fn test_connect() {
+        let cvars = Cvars::default();
+        let (mut server, mut conn) = start(&cvars);
+
+        // The player exists but gets a vehicle only after the client sends `Connect`.
+        let received = run(&cvars, &mut server, &mut conn);
+        let player_index = received
+            .iter()
+            .find_map(|msg| match msg {
+                ServerMessage::Init(init) => Some(init.local_player1_index),
+                _ => None,
+            })
+            .unwrap();
+        let spawned = |received: &[ServerMessage]| -> Vec<VehicleType> {
+            received
+                .iter()
+                .filter_map(|msg| match msg {
+                    ServerMessage::SpawnVehicle(init) if init.owner == player_index => {
+                        Some(init.veh_type)
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(spawned(&received), []);
+
+        connect(&mut conn, "player");
+        let received = run(&cvars, &mut server, &mut conn);
+        assert_eq!(spawned(&received), [VehicleType::Hovercraft]);
+    }
+
+    #[test]
+    fn test_rename() {
+        let cvars = Cvars::default();
+        let (mut server, mut conn) = start(&cvars);
+
+        let long = format!("a\nb{}", "c".repeat(100));
+        connect(&mut conn, &long);
+        for name in ["d", "e", "f", "g", "h"] {
+            connect(&mut conn, name);
+        }
+        let received = run(&cvars, &mut server, &mut conn);
+        let names: Vec<_> = received
+            .iter()
+            .filter_map(|msg| match msg {
+                ServerMessage::RenamePlayer { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        // Joining doesn't count against the rate limit, renaming does.
+        let sanitized = format!("ab{}", "c".repeat(cvars.sv_name_length_max - 2));
+        assert_eq!(names, [sanitized.as_str(), "d", "e", "f"]);
+    }
+
+    #[test]
+    fn test_observe_join() {
+        let cvars = Cvars::default();
+        let (mut server, mut conn) = start(&cvars);
+        connect(&mut conn, "player");
+        run(&cvars, &mut server, &mut conn);
+
+        let msg = ClientMessage::Observe;
+        Connection::<ServerMessage>::send(&mut conn, &net::serialize(msg)).unwrap();
+        let received = run(&cvars, &mut server, &mut conn);
+        let observed = received
+            .iter()
+            .any(|msg| matches!(msg, ServerMessage::PlayerObserve { .. }));
+        assert!(observed);
+
+        // Switching back right away is not allowed.
+        let msg = ClientMessage::Join;
+        Connection::<ServerMessage>::send(&mut conn, &net::serialize(msg)).unwrap();
+        let received = run(&cvars, &mut server, &mut conn);
+        let joined = received
+            .iter()
+            .any(|msg| matches!(msg, ServerMessage::PlayerJoin { .. }));
+        assert!(!joined);
+    }
+}
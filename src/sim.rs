@@ -0,0 +1,86 @@
+//! Headless bot-vs-bot matches for balance testing.
+//!
+//! Runs the server's gamelogic without a window or networking
+//! as fast as possible and writes stats about each match to a file.
+//! The results are reproducible - match N uses `d_seed + N`.
+
+use std::{error::Error, fmt::Write, fs, sync::OnceLock, time::Instant};
+
+use crate::{map, prelude::*, sys_ai};
+
+pub fn sim_main(mut cvars: Cvars) -> Result<(), Box<dyn Error>> {
+    if cvars.g_time_limit <= 0.0 {
+        return Err("sim needs g_time_limit, otherwise matches might never end".into());
+    }
+
+    let texture_list = fs::read_to_string("data/texture_list.txt")?;
+    let bot_profiles = fs::read_to_string("data/bot_profiles.txt")?;
+    let bot_profiles = sys_ai::parse_bot_profiles(&bot_profiles);
+    let map_paths: Vec<_> = cvars
+        .sim_maps
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if map_paths.is_empty() {
+        return Err("sim_maps is empty".into());
+    }
+
+    // Exactly the requested number of bots no matter the map size.
+    cvars.bots_max = cvars.sim_bots;
+    cvars.g_players_min = cvars.sim_bots;
+    cvars.g_players_max = cvars.g_players_max.max(cvars.sim_bots + 1);
+
+    let seed = cvars.d_seed;
+    let dt = 1.0 / cvars.sys_tickrate_fixed_fps;
+    let mut results = String::new();
+    for match_num in 0..cvars.sim_matches {
+        let map_path = &map_paths[match_num % map_paths.len()];
+        let map_text = fs::read_to_string(map_path)?;
+        let surfaces = map::parse_texture_list(&texture_list);
+        let map = map::parse_map(&map_text, surfaces, map_path);
+
+        cvars.d_seed = seed.wrapping_add(match_num as u64);
+        let mut server = Server::new(&cvars, map, bot_profiles.clone(), Vec::new(), None, clock);
+
+        let started = clock();
+        let mut real_time = 0.0;
+        while server.gs.match_state == MatchState::Playing {
+            real_time += dt;
+            server.update(&cvars, real_time);
+        }
+        let duration = clock() - started;
+        let game_time = server.gs.game_time - server.sg.stats.start_time;
+        dbg_logf!(
+            "Match {}/{} on {} took {:.2} s ({:.1} s of game time)",
+            match_num + 1,
+            cvars.sim_matches,
+            map_path,
+            duration,
+            game_time
+        );
+
+        writeln!(
+            results,
+            "match {} map {:?} seed {} game_time {:.1}",
+            match_num + 1,
+            map_path,
+            cvars.d_seed,
+            game_time
+        )?;
+        writeln!(results, "{}", server.sg.stats)?;
+
+        // Save after each match so nothing is lost if the sim is interrupted.
+        fs::write(&cvars.sim_results_path, &results)?;
+    }
+
+    dbg_logf!("Results saved to {}", cvars.sim_results_path);
+    Ok(())
+}
+
+/// Seconds since the first call.
+fn clock() -> f64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64()
+}
@@ -0,0 +1,180 @@
+//! Per-match statistics for balance testing.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::prelude::*;
+
+/// What happened during one match, collected on the server.
+#[derive(Debug, Clone, Default)]
+pub struct MatchStats {
+    /// Game time when the match started.
+    pub start_time: f64,
+    pub causes: FnvHashMap<DamageCause, CauseStats>,
+    pub vehicles: FnvHashMap<VehicleType, VehicleStats>,
+    /// Game time of the first damage each living vehicle took, used for time to kill.
+    first_damage: FnvHashMap<Index, f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CauseStats {
+    /// Including suicides and teamkills.
+    pub kills: u32,
+    /// Only the damage that actually removed HP, overkill is not counted.
+    pub damage: f64,
+    /// Sum of the times from the victims' first damage to the kill.
+    pub ttk_sum: f64,
+}
+
+/// Kills between different players by the vehicle types they were driving.
+#[derive(Debug, Clone, Default)]
+pub struct VehicleStats {
+    pub wins: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn new(start_time: f64) -> Self {
+        Self {
+            start_time,
+            ..Self::default()
+        }
+    }
+
+    pub fn add_damage(
+        &mut self,
+        vehicle_handle: Index,
+        cause: DamageCause,
+        amount: f64,
+        game_time: f64,
+    ) {
+        self.causes.entry(cause).or_default().damage += amount;
+        self.first_damage.entry(vehicle_handle).or_insert(game_time);
+    }
+
+    pub fn add_kill(
+        &mut self,
+        vehicle_handle: Index,
+        cause: DamageCause,
+        game_time: f64,
+        attacker_type: Option<VehicleType>,
+        victim_type: VehicleType,
+    ) {
+        let first_damage = self
+            .first_damage
+            .remove(&vehicle_handle)
+            .unwrap_or(game_time);
+        let cause_stats = self.causes.entry(cause).or_default();
+        cause_stats.kills += 1;
+        cause_stats.ttk_sum += game_time - first_damage;
+
+        if let Some(attacker_type) = attacker_type {
+            self.vehicles.entry(attacker_type).or_default().wins += 1;
+            self.vehicles.entry(victim_type).or_default().losses += 1;
+        }
+    }
+}
+
+impl CauseStats {
+    pub fn ttk_avg(&self) -> f64 {
+        if self.kills == 0 {
+            0.0
+        } else {
+            self.ttk_sum / self.kills as f64
+        }
+    }
+}
+
+impl VehicleStats {
+    /// Fraction of fights won, 0 if the vehicle type wasn't in any.
+    pub fn win_rate(&self) -> f64 {
+        let fights = self.wins + self.losses;
+        if fights == 0 {
+            0.0
+        } else {
+            self.wins as f64 / fights as f64
+        }
+    }
+}
+
+impl Display for MatchStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let weapons =
+            (0..Weapon::COUNT).map(|i| DamageCause::Weapon(Weapon::from_repr(i).unwrap()));
+        let causes = weapons.chain([DamageCause::Mine, DamageCause::SelfDestruct]);
+
+        writeln!(f, "cause kills damage avg_ttk")?;
+        for cause in causes {
+            let stats = self.causes.get(&cause).cloned().unwrap_or_default();
+            let name = match cause {
+                DamageCause::Weapon(weapon) => format!("{weapon:?}"),
+                _ => format!("{cause:?}"),
+            };
+            writeln!(
+                f,
+                "{} {} {:.1} {:.2}",
+                name,
+                stats.kills,
+                stats.damage,
+                stats.ttk_avg()
+            )?;
+        }
+
+        writeln!(f, "vehicle wins losses win_rate")?;
+        for i in 0.. {
+            let Some(veh_type) = VehicleType::from_repr(i) else {
+                break;
+            };
+            let stats = self.vehicles.get(&veh_type).cloned().unwrap_or_default();
+            writeln!(
+                f,
+                "{:?} {} {} {:.3}",
+                veh_type,
+                stats.wins,
+                stats.losses,
+                stats.win_rate()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_stats() {
+        let mut stats = MatchStats::new(0.0);
+        let victim = Index::DANGLING;
+        let rail = DamageCause::Weapon(Weapon::Rail);
+
+        stats.add_damage(victim, DamageCause::Weapon(Weapon::Mg), 10.0, 1.0);
+        stats.add_damage(victim, rail, 90.0, 3.0);
+        stats.add_kill(
+            victim,
+            rail,
+            3.0,
+            Some(VehicleType::Tank),
+            VehicleType::Hummer,
+        );
+
+        let rail_stats = &stats.causes[&rail];
+        assert_eq!(rail_stats.kills, 1);
+        assert_eq!(rail_stats.damage, 90.0);
+        assert_eq!(rail_stats.ttk_avg(), 2.0);
+        assert_eq!(stats.vehicles[&VehicleType::Tank].win_rate(), 1.0);
+        assert_eq!(stats.vehicles[&VehicleType::Hummer].win_rate(), 0.0);
+
+        // The next life starts counting from scratch.
+        stats.add_kill(
+            victim,
+            DamageCause::SelfDestruct,
+            10.0,
+            None,
+            VehicleType::Hummer,
+        );
+        assert_eq!(stats.causes[&DamageCause::SelfDestruct].ttk_avg(), 0.0);
+        assert_eq!(stats.vehicles[&VehicleType::Hummer].losses, 1);
+    }
+}
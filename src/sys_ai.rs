@@ -1,6 +1,9 @@
-//! Stub. So far bots move and shoot randomly.
+//! Bots - they find their way around the map using A* on tiles,
+//! pick visible enemies as targets and shoot at them with lead.
+//!
+//! They drive using the same `NetInput` as human players.
 
-use crate::prelude::*;
+use crate::{nav::path_length, prelude::*};
 
 impl ServerFrameCtx<'_> {
     pub fn sys_ai(&mut self) {
@@ -8,49 +11,640 @@ impl ServerFrameCtx<'_> {
             return;
         }
 
-        for (_, ai) in self.gs.ais.iter_mut() {
-            let player = &mut self.gs.players[ai.player];
-            let vehicle = &self.gs.vehicles[player.vehicle.unwrap()];
-
-            // keep moving forward if recently spawned
-            let age = self.gs.game_time - vehicle.spawn_time;
-            if age < 0.5 {
-                ai.movement = 1;
-            } else if self.sg.rng.gen_bool(0.01) {
-                let r: f64 = self.sg.rng.gen();
-                if r < 0.5 {
-                    ai.movement = 1;
-                } else if r < 0.65 {
-                    ai.movement = 0;
+        for ai_handle in self.gs.ais.collect_handles() {
+            let input = self.ai_input(ai_handle);
+            let player_handle = self.gs.ais[ai_handle].player;
+            self.gs.players[player_handle].input = input;
+        }
+    }
+
+    fn ai_input(&mut self, ai_handle: Index) -> NetInput {
+        let player_handle = self.gs.ais[ai_handle].player;
+        let player = &self.gs.players[player_handle];
+        // Input from the last frame, `input_prev` has already been overwritten.
+        let input_prev = player.input;
+        let mut input = NetInput::default();
+
+        let Some(vehicle_handle) = player.vehicle else {
+            return input;
+        };
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        if vehicle.destroyed() {
+            // Respawning needs a press and a release.
+            input.fire = !input_prev.fire;
+            return input;
+        }
+        let pos = vehicle.pos;
+        let angle = vehicle.angle;
+        if let Some(gm) = player
+            .guided_missile
+            .and_then(|gm| self.gs.projectiles.get(gm))
+        {
+            // The vehicle can't move while guiding so all input goes to the missile.
+            return self.ai_guide_missile(ai_handle, pos, gm.pos, gm.angle);
+        }
+        // The next missile needs a new path.
+        self.gs.ais[ai_handle].gm_path.clear();
+
+        let target = self.ai_pick_target(ai_handle, vehicle_handle);
+        self.gs.ais[ai_handle].target = target;
+
+        self.ai_update_path(ai_handle, vehicle_handle);
+
+        // Movement - follow the path, or go straight for the target once there.
+        let ai = &mut self.gs.ais[ai_handle];
+        while let Some(&waypoint) = ai.path.first() {
+            if self.map.tile_center(waypoint).distance(pos) < self.cvars.ai_waypoint_radius {
+                ai.path.remove(0);
+            } else {
+                break;
+            }
+        }
+        let dest = match (ai.path.first(), target) {
+            (Some(&waypoint), _) => Some(self.map.tile_center(waypoint)),
+            (None, Some(target)) => Some(self.gs.vehicles[target].pos),
+            (None, None) => None,
+        };
+        if let Some(dest) = dest {
+            let delta = angle.delta_angle((dest - pos).to_angle());
+            steer(&mut input, delta);
+            input.up = delta.abs() < self.cvars.ai_drive_angle_deg.to_radians();
+        }
+
+        // Getting stuck on walls and other vehicles happens, back off and try again.
+        if self.gs.game_time - ai.stuck_check_time >= self.cvars.ai_stuck_time {
+            let moved = ai.stuck_check_pos.distance(pos);
+            if moved < self.cvars.ai_stuck_distance && self.gs.game_time >= ai.reverse_end {
+                ai.reverse_end = self.gs.game_time + self.cvars.ai_unstuck_time;
+                ai.next_repath_time = ai.reverse_end;
+            }
+            ai.stuck_check_pos = pos;
+            ai.stuck_check_time = self.gs.game_time;
+        }
+        if self.gs.game_time < ai.reverse_end {
+            input.up = false;
+            input.down = true;
+        }
+
+        // Cautious bots keep their distance.
+        if let Some(target) = target {
+            let keep_distance = self.cvars.ai_sight_range * (1.0 - ai.profile.aggression) / 2.0;
+            if self.gs.vehicles[target].pos.distance(pos) < keep_distance {
+                input.up = false;
+            }
+        }
+
+        // Own mines hurt too, don't drive over them.
+        let mine_distance = self.cvars.g_mine_trigger_radius + self.cvars.ai_splash_margin;
+        for (_, mine) in self.gs.mines.iter() {
+            let rel = mine.pos - pos;
+            if mine.owner == player_handle && mine.owner_left && rel.magnitude() < mine_distance {
+                if angle.delta_angle(rel.to_angle()).abs() < PI / 2.0 {
+                    input.up = false;
                 } else {
-                    ai.movement = -1;
+                    input.down = false;
                 }
             }
+        }
+
+        if self.gs.game_time >= ai.next_aim_error_time {
+            let normal = Normal::new(0.0, ai.profile.aim_error_deg.to_radians()).unwrap();
+            ai.aim_error = self.sg.rng.sample(normal);
+            ai.next_aim_error_time = self.gs.game_time + self.cvars.ai_aim_error_interval;
+        }
+        let aim_error = ai.aim_error;
+
+        // Aiming and shooting
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let player = &self.gs.players[player_handle];
+        let weapon = player.cur_weapon;
+        let (hardpoint, _) = self.cvars.g_hardpoint(vehicle.veh_type, weapon);
+
+        let aim_angle = target.map(|target| {
+            let target = &self.gs.vehicles[target];
+            let speed = self.cvars.g_weapon_speed(weapon);
+            let vel_factor = self.cvars.g_weapon_vehicle_velocity_factor(weapon);
+            let rel_vel = target.vel - vel_factor * vehicle.vel;
+            intercept(target.pos - pos, rel_vel, speed).to_angle() + aim_error
+        });
+
+        // The turret only turns in steps so it does the coarse aiming
+        // and the chassis does the rest.
+        let turret_wanted = match (hardpoint, aim_angle) {
+            (Hardpoint::Turret, Some(aim_angle)) => {
+                let step = self.cvars.g_turret_turn_step_angle_deg.to_radians();
+                let rel = angle.delta_angle(aim_angle);
+                (rel / step).round() * step
+            }
+            // Keep the turret forward when it's not needed
+            // so it's ready for when the bot switches weapons.
+            _ => 0.0,
+        };
+        let turret_delta = vehicle.turret_angle_wanted.delta_angle(turret_wanted);
+        let half_step = self.cvars.g_turret_turn_step_angle_deg.to_radians() / 2.0;
+        // Turret turning is triggered on press so release the key in between.
+        let ai = &mut self.gs.ais[ai_handle];
+        if self.gs.game_time >= ai.next_turret_time {
+            input.turret_left = turret_delta < -half_step && !input_prev.turret_left;
+            input.turret_right = turret_delta > half_step && !input_prev.turret_right;
+            if input.turret_left || input.turret_right {
+                ai.next_turret_time = self.gs.game_time + ai.profile.turret_interval;
+            }
+        }
+
+        let shot_angle = match hardpoint {
+            Hardpoint::Chassis => angle,
+            Hardpoint::Turret => angle + vehicle.turret_angle_current,
+        };
+        if let Some(aim_angle) = aim_angle {
+            let chassis_delta = (angle + turret_wanted).delta_angle(aim_angle);
+            steer(&mut input, chassis_delta);
+
+            let error = shot_angle.delta_angle(aim_angle).abs();
+            input.fire = error < self.cvars.ai_fire_angle_deg.to_radians()
+                && self.ai_splash_safe(ai_handle, vehicle_handle, weapon, shot_angle);
+        } else if weapon == Weapon::Gm && self.ai_gm_launch(ai_handle, pos, shot_angle) {
+            // Nobody to shoot at directly but there's an enemy around a corner.
+            input.fire = self.ai_splash_safe(ai_handle, vehicle_handle, weapon, shot_angle);
+        }
+
+        // Mines for enemies on our tail.
+        if let Some(target) = target {
+            let rel = self.gs.vehicles[target].pos - pos;
+            let behind = angle.delta_angle(rel.to_angle()).abs() > PI * 3.0 / 4.0;
+            let close = rel.magnitude() < self.cvars.g_mine_trigger_radius * 4.0;
+            input.mine = behind && close && !input_prev.mine;
+        }
+
+        // Weapons are switched one step per press so pick the shorter direction.
+        let wanted = self.ai_choose_weapon(ai_handle, vehicle_handle);
+        let steps_next = (wanted as usize + Weapon::COUNT - weapon as usize) % Weapon::COUNT;
+        if steps_next != 0 {
+            if steps_next <= Weapon::COUNT / 2 {
+                input.next_weapon = !input_prev.next_weapon;
+            } else {
+                input.prev_weapon = !input_prev.prev_weapon;
+            }
+        }
+        // Switching happens before shooting so the decision above would be for the wrong weapon.
+        if input.next_weapon || input.prev_weapon {
+            input.fire = false;
+        }
+
+        input
+    }
+
+    /// Pick a weapon based on the situation, falling back to the profile's favorites.
+    ///
+    /// Only weapons which are not reloading are considered
+    /// so bots switch to something else while waiting.
+    fn ai_choose_weapon(&self, ai_handle: Index, vehicle_handle: Index) -> Weapon {
+        let ai = &self.gs.ais[ai_handle];
+        let player = &self.gs.players[ai.player];
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+
+        let mut wanted = Vec::new();
+        let mut target_distance = None;
+        if let Some(target_handle) = ai.target {
+            let target = &self.gs.vehicles[target_handle];
+            let distance = target.pos.distance(vehicle.pos);
+            target_distance = Some(distance);
+
+            // Cluster bombs spread so they're best against groups.
+            let group = self
+                .gs
+                .vehicles
+                .iter()
+                .filter(|(_, other)| {
+                    !other.destroyed()
+                        && self.is_enemy(player, other.owner)
+                        && other.pos.distance(target.pos) <= self.cvars.ai_weapon_group_radius
+                })
+                .count();
+            if group >= self.cvars.ai_weapon_group_size {
+                wanted.push(Weapon::Cb);
+            }
+
+            // Homing missiles lock on only when launched roughly towards the target.
+            let (hardpoint, _) = self.cvars.g_hardpoint(vehicle.veh_type, Weapon::Hm);
+            let launch_angle = match hardpoint {
+                Hardpoint::Chassis => vehicle.angle,
+                Hardpoint::Turret => vehicle.angle + vehicle.turret_angle_current,
+            };
+            let target_angle = (target.pos - vehicle.pos).to_angle();
+            if launch_angle.delta_angle(target_angle).abs()
+                < self.cvars.g_homing_missile_angle_detect
+            {
+                wanted.push(Weapon::Hm);
+            }
+
+            if distance >= self.cvars.ai_weapon_range_long {
+                wanted.push(Weapon::Rail);
+            } else if distance <= self.cvars.ai_weapon_range_short {
+                wanted.push(Weapon::Mg);
+            }
+        } else if ai.hunted.is_some() {
+            wanted.push(Weapon::Gm);
+        }
+        wanted.extend(&ai.profile.weapons);
+
+        // Splash damage would hurt the bot too if the target is close.
+        let usable = |weapon: &Weapon| {
+            let ready = matches!(vehicle.ammos[*weapon as usize], Ammo::Loaded(..));
+            let safe = target_distance.map_or(true, |distance| {
+                distance >= self.ai_splash_distance(*weapon)
+            });
+            ready && safe
+        };
+        wanted.into_iter().find(usable).unwrap_or(player.cur_weapon)
+    }
+
+    /// How far from the bot the weapon's explosion has to be not to damage it.
+    fn ai_splash_distance(&self, weapon: Weapon) -> f64 {
+        if self.cvars.g_weapon_explosion_damage(weapon) <= 0.0 {
+            return 0.0;
+        }
+        self.cvars.g_weapon_explosion_radius(weapon) + self.cvars.ai_splash_margin
+    }
+
+    /// Whether a shot would explode far enough from the bot -
+    /// the target is not too close and there's no wall right in front.
+    fn ai_splash_safe(
+        &self,
+        ai_handle: Index,
+        vehicle_handle: Index,
+        weapon: Weapon,
+        shot_angle: f64,
+    ) -> bool {
+        let distance = self.ai_splash_distance(weapon);
+        if distance <= 0.0 {
+            return true;
+        }
+
+        let vehicle = &self.gs.vehicles[vehicle_handle];
+        let target = self.gs.ais[ai_handle].target;
+        let target_close = target
+            .is_some_and(|target| self.gs.vehicles[target].pos.distance(vehicle.pos) < distance);
+        if target_close {
+            return false;
+        }
+
+        // Same as in `sys_shooting` - weapons are often off to the side.
+        let (hardpoint, weapon_offset) = self.cvars.g_hardpoint(vehicle.veh_type, weapon);
+        let origin = match hardpoint {
+            Hardpoint::Chassis => vehicle.pos + weapon_offset.rotated_z(shot_angle),
+            Hardpoint::Turret => {
+                let turret_offset = self.cvars.g_vehicle_turret_offset_chassis(vehicle.veh_type);
+                vehicle.pos
+                    + turret_offset.rotated_z(vehicle.angle)
+                    + weapon_offset.rotated_z(shot_angle)
+            }
+        };
 
-            if self.sg.rng.gen_bool(0.03) {
-                ai.turning = self.sg.rng.gen_range(-1..=1);
+        // Projectiles inherit some of the vehicle's velocity so they can drift sideways at first.
+        let dir = shot_angle.to_vec2f();
+        let vel = dir * self.cvars.g_weapon_speed(weapon)
+            + vehicle.vel * self.cvars.g_weapon_vehicle_velocity_factor(weapon);
+        [dir, vel.normalized()].into_iter().all(|dir| {
+            let end = origin + dir * distance;
+            self.map.is_wall_trace(origin, end).is_none()
+        })
+    }
+
+    /// Whether launching a guided missile now would make sense -
+    /// the hunted enemy is not too far along the path
+    /// and the bot is facing the way there.
+    fn ai_gm_launch(&self, ai_handle: Index, pos: Vec2f, shot_angle: f64) -> bool {
+        let ai = &self.gs.ais[ai_handle];
+        if ai.hunted.is_none() || ai.path.is_empty() {
+            return false;
+        }
+
+        let first = self.map.tile_center(ai.path[0]);
+        let distance = pos.distance(first) + path_length(&ai.path);
+        if distance > self.cvars.ai_gm_range {
+            return false;
+        }
+
+        shot_angle.delta_angle((first - pos).to_angle()).abs()
+            < self.cvars.ai_drive_angle_deg.to_radians()
+    }
+
+    /// Steer the guided missile towards the target, following a path around walls if needed.
+    fn ai_guide_missile(
+        &mut self,
+        ai_handle: Index,
+        pos: Vec2f,
+        gm_pos: Vec2f,
+        gm_angle: f64,
+    ) -> NetInput {
+        let mut input = NetInput::default();
+
+        let ai = &self.gs.ais[ai_handle];
+        let target_pos = ai
+            .target
+            .or(ai.hunted)
+            .and_then(|handle| self.gs.vehicles.get(handle))
+            .filter(|target| !target.destroyed())
+            .map(|target| target.pos);
+        let Some(target_pos) = target_pos else {
+            return input;
+        };
+
+        // The bot can't move while guiding so keep the explosion away from it.
+        // Launching checks there's room in front so first just fly straight
+        // and later steer away if the target comes too close.
+        let splash_distance = self.ai_splash_distance(Weapon::Gm);
+        if gm_pos.distance(pos) < splash_distance {
+            return input;
+        }
+        if target_pos.distance(pos) < splash_distance {
+            steer(&mut input, gm_angle.delta_angle((gm_pos - pos).to_angle()));
+            return input;
+        }
+
+        let dest = if self.map.is_wall_trace(gm_pos, target_pos).is_none() {
+            target_pos
+        } else {
+            self.ai_gm_waypoint(ai_handle, gm_pos, target_pos)
+                .unwrap_or(target_pos)
+        };
+        steer(&mut input, gm_angle.delta_angle((dest - gm_pos).to_angle()));
+        input
+    }
+
+    /// Next tile on the guided missile's path to the target.
+    ///
+    /// Like when driving, the path is only recomputed every `ai_repath_interval`.
+    fn ai_gm_waypoint(
+        &mut self,
+        ai_handle: Index,
+        gm_pos: Vec2f,
+        target_pos: Vec2f,
+    ) -> Option<Vec2f> {
+        let ai = &self.gs.ais[ai_handle];
+        if self.gs.game_time >= ai.next_gm_repath_time || ai.gm_path.is_empty() {
+            let nav = &self.sg.nav;
+            let path = nav
+                .nearest_walkable(self.map, gm_pos)
+                .zip(nav.nearest_walkable(self.map, target_pos))
+                .and_then(|(start, goal)| nav.find_path(start, goal))
+                .unwrap_or_default();
+
+            let ai = &mut self.gs.ais[ai_handle];
+            ai.gm_path = path;
+            ai.next_gm_repath_time = self.gs.game_time + self.cvars.ai_repath_interval;
+        }
+
+        // Missiles are too fast to reliably pass close to tile centers
+        // so move on as soon as the missile is in the waypoint's tile.
+        let ai = &mut self.gs.ais[ai_handle];
+        let cur_tile = self.map.tile_pos(gm_pos).index;
+        if let Some(reached) = ai.gm_path.iter().position(|&tile| tile == cur_tile) {
+            ai.gm_path.drain(..=reached);
+        }
+        ai.gm_path
+            .first()
+            .map(|&waypoint| self.map.tile_center(waypoint))
+    }
+
+    /// Keep the current target while it's visible.
+    /// Otherwise react to the nearest visible enemy after the profile's reaction time.
+    fn ai_pick_target(&mut self, ai_handle: Index, vehicle_handle: Index) -> Option<Index> {
+        let ai = &self.gs.ais[ai_handle];
+        if let Some(target) = ai.target {
+            if self.sees_enemy(ai.player, vehicle_handle, target) {
+                return Some(target);
             }
+        }
+
+        let visible = self
+            .gs
+            .vehicles
+            .iter()
+            .filter(|&(handle, _)| self.sees_enemy(ai.player, vehicle_handle, handle))
+            .map(|(handle, other)| (handle, other.pos))
+            .min_by(|(_, a), (_, b)| {
+                let pos = self.gs.vehicles[vehicle_handle].pos;
+                a.distance(pos).total_cmp(&b.distance(pos))
+            })
+            .map(|(handle, _)| handle);
+
+        let ai = &mut self.gs.ais[ai_handle];
+        let Some(visible) = visible else {
+            ai.spotted = None;
+            return None;
+        };
+        match ai.spotted {
+            Some((spotted, time)) if spotted == visible => {
+                if self.gs.game_time >= time + ai.profile.reaction_time {
+                    ai.spotted = None;
+                    Some(visible)
+                } else {
+                    None
+                }
+            }
+            _ => {
+                ai.spotted = Some((visible, self.gs.game_time));
+                None
+            }
+        }
+    }
 
-            if !ai.firing && self.sg.rng.gen_bool(0.01) {
-                ai.firing = true;
-            } else if ai.firing && self.sg.rng.gen_bool(0.03) {
-                ai.firing = false;
+    /// Whether the other vehicle is an enemy in sight range and not behind walls.
+    ///
+    /// External bot controllers see the same enemies as built-in bots.
+    pub fn sees_enemy(
+        &self,
+        player_handle: Index,
+        vehicle_handle: Index,
+        other_handle: Index,
+    ) -> bool {
+        let player = &self.gs.players[player_handle];
+        let pos = self.gs.vehicles[vehicle_handle].pos;
+        let Some(other) = self.gs.vehicles.get(other_handle) else {
+            return false;
+        };
+
+        other_handle != vehicle_handle
+            && !other.destroyed()
+            && self.is_enemy(player, other.owner)
+            && other.pos.distance(pos) <= self.cvars.ai_sight_range
+            && self.map.is_wall_trace(pos, other.pos).is_none()
+    }
+
+    fn is_enemy(&self, player: &Player, other_handle: Index) -> bool {
+        let other = &self.gs.players[other_handle];
+        player.team.is_none() || player.team != other.team
+    }
+
+    /// Periodically find a new path - towards the target if there is one,
+    /// otherwise towards the nearest enemy or a random place on the map.
+    fn ai_update_path(&mut self, ai_handle: Index, vehicle_handle: Index) {
+        let ai = &self.gs.ais[ai_handle];
+        if self.gs.game_time < ai.next_repath_time && !ai.path.is_empty() {
+            return;
+        }
+
+        let player = &self.gs.players[ai.player];
+        let pos = self.gs.vehicles[vehicle_handle].pos;
+        // Aggressive bots hunt enemies they can't see, the rest wander around.
+        let hunting = self.sg.rng.gen_bool(ai.profile.aggression.clamp(0.0, 1.0));
+        let hunted = ai.target.or_else(|| {
+            if !hunting {
+                return None;
             }
+            self.gs
+                .vehicles
+                .iter()
+                .filter(|&(handle, other)| {
+                    handle != vehicle_handle
+                        && !other.destroyed()
+                        && self.is_enemy(player, other.owner)
+                })
+                .min_by(|(_, a), (_, b)| a.pos.distance(pos).total_cmp(&b.pos.distance(pos)))
+                .map(|(handle, _)| handle)
+        });
+        let goal_pos = hunted
+            .map(|handle| self.gs.vehicles[handle].pos)
+            .unwrap_or_else(|| self.map.random_nonwall(&mut self.sg.rng).0);
 
-            player.input = NetInput {
-                up: ai.movement == 1,
-                down: ai.movement == -1,
-                left: ai.turning == -1,
-                right: ai.turning == 1,
-                turret_left: self.sg.rng.gen_bool(0.01),
-                turret_right: self.sg.rng.gen_bool(0.01),
-                prev_weapon: self.sg.rng.gen_bool(0.02),
-                next_weapon: self.sg.rng.gen_bool(0.01),
-                fire: ai.firing,
-                mine: self.sg.rng.gen_bool(0.001),
-                self_destruct: self.sg.rng.gen_bool(0.0001),
-                horn: self.sg.rng.gen_bool(0.0001),
+        let nav = &self.sg.nav;
+        let path = nav
+            .nearest_walkable(self.map, pos)
+            .zip(nav.nearest_walkable(self.map, goal_pos))
+            .and_then(|(start, goal)| nav.find_path(start, goal))
+            .unwrap_or_default();
+
+        let ai = &mut self.gs.ais[ai_handle];
+        // The first tile is where the bot already is.
+        ai.path = path.into_iter().skip(1).collect();
+        ai.hunted = hunted;
+        ai.next_repath_time = self.gs.game_time + self.cvars.ai_repath_interval;
+    }
+}
+
+/// Parse `data/bot_profiles.txt`.
+///
+/// Each line is `name reaction_time aim_error_deg turret_interval aggression weapons`
+/// where weapons are comma separated. Lines starting with `#` are comments.
+pub fn parse_bot_profiles(text: &str) -> Vec<BotProfile> {
+    text.replace("\r\n", "\n")
+        .split_terminator('\n')
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next().unwrap().to_owned();
+            let reaction_time = parts.next().unwrap().parse().unwrap();
+            let aim_error_deg = parts.next().unwrap().parse().unwrap();
+            let turret_interval = parts.next().unwrap().parse().unwrap();
+            let aggression = parts.next().unwrap().parse().unwrap();
+            let weapons = parts
+                .next()
+                .unwrap()
+                .split(',')
+                .map(|weapon| match weapon {
+                    "mg" => Weapon::Mg,
+                    "rail" => Weapon::Rail,
+                    "cb" => Weapon::Cb,
+                    "rockets" => Weapon::Rockets,
+                    "hm" => Weapon::Hm,
+                    "gm" => Weapon::Gm,
+                    "bfg" => Weapon::Bfg,
+                    _ => panic!("Unknown weapon {weapon:?} in bot profile {name:?}"),
+                })
+                .collect();
+            BotProfile {
+                name,
+                reaction_time,
+                aim_error_deg,
+                turret_interval,
+                aggression,
+                weapons,
             }
+        })
+        .collect()
+}
+
+/// Turn towards the angle, negative delta means left.
+fn steer(input: &mut NetInput, delta: f64) {
+    // Don't wiggle when already roughly facing the right way.
+    let deadzone = 2.0_f64.to_radians();
+    input.left = delta < -deadzone;
+    input.right = delta > deadzone;
+}
+
+/// Where to aim so a projectile with the given speed hits a target moving at constant velocity.
+///
+/// Both position and velocity are relative to the shooter.
+/// If the projectile can't catch up, aims directly at the target.
+fn intercept(rel_pos: Vec2f, rel_vel: Vec2f, speed: f64) -> Vec2f {
+    // |rel_pos + rel_vel * t| = speed * t
+    let a = rel_vel.dot(rel_vel) - speed * speed;
+    let b = 2.0 * rel_pos.dot(rel_vel);
+    let c = rel_pos.dot(rel_pos);
+
+    let t = if a.abs() < 1e-9 {
+        if b < 0.0 {
+            -c / b
+        } else {
+            return rel_pos;
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return rel_pos;
+        }
+        let sqrt = discriminant.sqrt();
+        let t1 = (-b - sqrt) / (2.0 * a);
+        let t2 = (-b + sqrt) / (2.0 * a);
+        match (t1 > 0.0, t2 > 0.0) {
+            (true, true) => t1.min(t2),
+            (true, false) => t1,
+            (false, true) => t2,
+            (false, false) => return rel_pos,
         }
+    };
+
+    rel_pos + rel_vel * t
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_bot_profiles() {
+        let text = fs::read_to_string("data/bot_profiles.txt").unwrap();
+        let profiles = parse_bot_profiles(&text);
+        assert!(profiles.len() >= 2);
+        for pair in profiles.windows(2) {
+            assert!(pair[0].aim_error_deg >= pair[1].aim_error_deg);
+        }
+        for profile in &profiles {
+            assert!((0.0..=1.0).contains(&profile.aggression));
+            assert!(!profile.weapons.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_intercept() {
+        // Stationary target
+        let aim = intercept(Vec2f::new(100.0, 0.0), Vec2f::zero(), 50.0);
+        assert_eq!(aim, Vec2f::new(100.0, 0.0));
+
+        // Crossing target - the projectile and target arrive at the same time.
+        let rel_pos = Vec2f::new(300.0, 0.0);
+        let rel_vel = Vec2f::new(0.0, 100.0);
+        let aim = intercept(rel_pos, rel_vel, 500.0);
+        let t = aim.magnitude() / 500.0;
+        assert!((rel_pos + rel_vel * t).distance(aim) < 0.001);
+        assert!(aim.y > 0.0);
+
+        // Target is too fast and moving away.
+        let aim = intercept(rel_pos, Vec2f::new(1000.0, 0.0), 500.0);
+        assert_eq!(aim, rel_pos);
     }
 }
@@ -6,13 +6,257 @@
 
 use vek::LineSegment2;
 
-use crate::prelude::*;
+use crate::{map::SurfaceKind, prelude::*, stats::MatchStats};
 
 impl ServerFrameCtx<'_> {
+    /// End the match when a limit is reached and start a new one after the intermission.
+    pub fn sys_match(&mut self) {
+        match self.gs.match_state {
+            MatchState::Playing => {
+                if self.match_limit_reached() {
+                    self.end_match();
+                }
+            }
+            MatchState::Intermission { end_time } => {
+                if self.gs.game_time >= end_time {
+                    self.start_match();
+                }
+            }
+        }
+    }
+
+    fn match_limit_reached(&self) -> bool {
+        if self.gs.game_time >= self.gs.time_limit {
+            return true;
+        }
+
+        match self.gs.game_mode {
+            GameMode::Ffa(Ffa { kill_limit }) => {
+                kill_limit > 0
+                    && self
+                        .gs
+                        .players
+                        .iter()
+                        .any(|(_, player)| player.score.kills >= kill_limit)
+            }
+            GameMode::Tw(Tw { kill_limit }) => {
+                kill_limit > 0
+                    && self
+                        .gs
+                        .team_scores
+                        .iter()
+                        .any(|team_score| team_score.kills >= kill_limit)
+            }
+            GameMode::Ctc(Ctc { capture_limit }) => {
+                capture_limit > 0
+                    && self
+                        .gs
+                        .team_scores
+                        .iter()
+                        .any(|team_score| team_score.captures >= capture_limit)
+            }
+        }
+    }
+
+    fn end_match(&mut self) {
+        let end_time = self.gs.game_time + self.cvars.g_intermission_duration;
+        self.gs.match_state = MatchState::Intermission { end_time };
+
+        dbg_logf!("Match over at {:.03}", self.gs.game_time);
+
+        let scores = self
+            .gs
+            .players
+            .iter()
+            .map(|(handle, player)| PlayerScore {
+                index: handle.slot(),
+                score: player.score.clone(),
+            })
+            .collect();
+        let msg = ServerMessage::MatchEnd(MatchEnd {
+            scores,
+            team_scores: self.gs.team_scores.clone(),
+            next_match_time: end_time,
+        });
+        self.net_send_all(msg);
+    }
+
+    /// Reset limits, scores and projectiles and give everyone a new vehicle.
+    ///
+    /// The game mode is read from cvars here so changes take effect in the next match.
+    pub fn start_match(&mut self) {
+        self.sg.stats = MatchStats::new(self.gs.game_time);
+        self.gs.time_limit = if self.cvars.g_time_limit > 0.0 {
+            self.gs.game_time + self.cvars.g_time_limit
+        } else {
+            f64::INFINITY
+        };
+        self.gs.game_mode = match self.cvars.g_game_mode {
+            GameModeKind::Ffa => GameMode::Ffa(Ffa {
+                kill_limit: self.cvars.g_ffa_kill_limit,
+            }),
+            GameModeKind::Tw => GameMode::Tw(Tw {
+                kill_limit: self.cvars.g_tw_kill_limit,
+            }),
+            GameModeKind::Ctc => GameMode::Ctc(Ctc {
+                capture_limit: self.cvars.g_ctc_capture_limit,
+            }),
+        };
+        self.gs.match_state = MatchState::Playing;
+        self.gs.team_scores = Default::default();
+
+        // Players keep their teams between matches unless the game mode changes.
+        for player_handle in self.gs.players.collect_handles() {
+            if self.gs.players[player_handle].state != PlayerState::Playing {
+                continue;
+            }
+            let has_team = self.gs.players[player_handle].team.is_some();
+            if has_team != self.gs.game_mode.has_teams() {
+                // Unassign first so the player isn't counted when picking.
+                self.gs.players[player_handle].team = None;
+                self.gs.players[player_handle].team = self.pick_team();
+            }
+        }
+        let teams = self
+            .gs
+            .players
+            .iter()
+            .map(|(handle, player)| PlayerTeam {
+                index: handle.slot(),
+                team: player.team,
+            })
+            .collect();
+
+        // Leftovers from the previous match simply disappear, no explosions.
+        self.gs.projectiles.clear();
+        self.gs.mines.clear();
+
+        self.reset_cow();
+        if matches!(self.gs.game_mode, GameMode::Ctc(_)) && self.gs.cow.is_none() {
+            dbg_logf!("WARNING: map doesn't have bases for both teams, there will be no cow");
+        }
+
+        let msg = ServerMessage::MatchStart(MatchStart {
+            time_limit: self.gs.time_limit,
+            game_mode: self.gs.game_mode.clone(),
+            teams,
+        });
+        self.net_send_all(msg);
+
+        for player_handle in self.gs.players.collect_handles() {
+            let player = &mut self.gs.players[player_handle];
+            player.score = Score::default();
+            player.respawn = Respawn::No;
+            player.guided_missile = None;
+
+            // Remove the old vehicle right before spawning the new one
+            // so it reuses the same slot and clients replace it.
+            if let Some(vehicle_handle) = player.vehicle {
+                self.gs.vehicles.remove(vehicle_handle).unwrap();
+                self.spawn_vehicle(player_handle, true);
+            }
+        }
+    }
+
+    /// Capture The Cow - picking up, dropping and capturing.
+    pub fn sys_cow(&mut self) {
+        let Some(cow) = &mut self.gs.cow else {
+            return;
+        };
+
+        if let Some(carrier_handle) = cow.carrier {
+            let carrier = self.gs.players.get(carrier_handle);
+            let vehicle = carrier
+                .and_then(|player| player.vehicle)
+                .and_then(|vehicle_handle| self.gs.vehicles.get(vehicle_handle))
+                .filter(|vehicle| !vehicle.destroyed());
+
+            let Some(vehicle) = vehicle else {
+                // The carrier died, respawned or disconnected.
+                // While carried, the cow moves with the carrier so it drops at their last position.
+                cow.carrier = None;
+                cow.drop_time = Some(self.gs.game_time);
+                let msg = ServerMessage::CowDrop { pos: cow.pos };
+                self.net_send_all(msg);
+                return;
+            };
+
+            cow.pos = vehicle.pos;
+
+            let tile_pos = self.map.tile_pos(cow.pos);
+            let team = carrier.unwrap().team;
+            if self.map.surface_at_index(tile_pos.index).kind == SurfaceKind::Base
+                && Some(self.map.base_team(tile_pos.index)) == team
+            {
+                self.update_score_capture(carrier_handle);
+                let msg = ServerMessage::CowCapture {
+                    player: carrier_handle.slot(),
+                };
+                self.net_send_all(msg);
+            }
+        } else {
+            if let Some(drop_time) = cow.drop_time {
+                if self.cvars.g_ctc_cow_return_delay > 0.0
+                    && drop_time + self.cvars.g_ctc_cow_return_delay < self.gs.game_time
+                {
+                    self.reset_cow();
+                    self.net_send_all(ServerMessage::CowReturn);
+                    return;
+                }
+            }
+
+            let radius2 = self.cvars.g_ctc_cow_pickup_radius.powi(2);
+            let pickup = self.gs.vehicles.iter().find(|(_, vehicle)| {
+                !vehicle.destroyed() && vehicle.pos.distance_squared(cow.pos) <= radius2
+            });
+            if let Some((_, vehicle)) = pickup {
+                cow.carrier = Some(vehicle.owner);
+                cow.drop_time = None;
+                let msg = ServerMessage::CowPickup {
+                    player: vehicle.owner.slot(),
+                };
+                self.net_send_all(msg);
+            }
+        }
+    }
+
+    /// The team with fewer players or None if the game mode has no teams.
+    pub fn pick_team(&self) -> Option<Team> {
+        if !self.gs.game_mode.has_teams() {
+            return None;
+        }
+
+        let mut counts = [0; 2];
+        for (_, player) in self.gs.players.iter() {
+            if let Some(team) = player.team {
+                counts[team as usize] += 1;
+            }
+        }
+        if counts[Team::Blue as usize] < counts[Team::Red as usize] {
+            Some(Team::Blue)
+        } else {
+            Some(Team::Red)
+        }
+    }
+
     pub fn sys_respawning(&mut self) {
         for player_handle in self.gs.players.collect_handles() {
             let player = &mut self.gs.players[player_handle];
-            let vehicle_handle = player.vehicle.unwrap();
+            if player.state != PlayerState::Playing {
+                continue;
+            }
+            // Players get their first vehicle after connecting.
+            // Those who joined from observing while their respawn delay was running
+            // get it once it ends.
+            let Some(vehicle_handle) = player.vehicle else {
+                if player.respawn == Respawn::Scheduled
+                    && player.death_time + self.cvars.g_respawn_delay < self.gs.game_time
+                {
+                    player.respawn = Respawn::No;
+                    self.spawn_vehicle(player_handle, true);
+                }
+                continue;
+            };
             if !self.gs.vehicles[vehicle_handle].destroyed() {
                 continue;
             }
@@ -41,10 +285,120 @@ impl ServerFrameCtx<'_> {
         }
     }
 
+    /// The player's preferred vehicle if allowed, otherwise a random allowed one.
+    fn pick_vehicle_type(&mut self, player_handle: Index) -> VehicleType {
+        let mut allowed: Vec<_> = [
+            VehicleType::Tank,
+            VehicleType::Hovercraft,
+            VehicleType::Hummer,
+        ]
+        .into_iter()
+        .filter(|&veh_type| self.cvars.g_vehicle_allowed(veh_type))
+        .collect();
+        if allowed.is_empty() {
+            dbg_logf!("WARNING: no vehicles allowed, allowing all");
+            allowed = vec![
+                VehicleType::Tank,
+                VehicleType::Hovercraft,
+                VehicleType::Hummer,
+            ];
+        }
+
+        let preferred = self.gs.players[player_handle]
+            .vehicle_preference
+            .vehicle_type();
+        match preferred {
+            Some(veh_type) if !self.cvars.g_vehicle_force_random && allowed.contains(&veh_type) => {
+                veh_type
+            }
+            _ => *allowed.choose(&mut self.sg.rng).unwrap(),
+        }
+    }
+
+    /// Choose the best spawn for the player. Returns (pos, angle).
+    ///
+    /// Spawns are scored by distance and line of sight to enemies,
+    /// nearby projectiles and distance from where the player died.
+    /// Occupied spawns are skipped, if all are occupied,
+    /// the one furthest from any vehicle is used.
+    fn pick_spawn(&mut self, player_handle: Index) -> (Vec2f, f64) {
+        if self.map.spawns().is_empty() {
+            dbg_logf!("WARNING: no spawns in map, using a random nonwall");
+            return self.map.random_nonwall(&mut self.sg.rng);
+        }
+
+        let player = &self.gs.players[player_handle];
+        let team = player.team;
+        let death_pos = player.death_pos;
+
+        let living_vehicles: Vec<_> = self
+            .gs
+            .vehicles
+            .iter()
+            .filter(|(_, vehicle)| !vehicle.destroyed() && vehicle.owner != player_handle)
+            .map(|(_, vehicle)| vehicle)
+            .collect();
+        let is_enemy = |vehicle: &Vehicle| {
+            let other_team = self.gs.players[vehicle.owner].team;
+            team.is_none() || team != other_team
+        };
+
+        let dist_max = self.cvars.g_spawn_distance_max;
+        let mut best: Option<(Vec2u, f64)> = None;
+        let mut fallback: Option<(Vec2u, f64)> = None;
+        for &spawn in self.map.spawns() {
+            let pos = self.map.tile_center(spawn);
+
+            let nearest_vehicle = living_vehicles
+                .iter()
+                .map(|vehicle| vehicle.pos.distance(pos))
+                .fold(f64::INFINITY, f64::min);
+            if nearest_vehicle <= self.cvars.g_spawn_occupied_radius {
+                // Strictly greater so ties go to the first spawn.
+                if fallback.map_or(true, |(_, dist)| nearest_vehicle > dist) {
+                    fallback = Some((spawn, nearest_vehicle));
+                }
+                continue;
+            }
+
+            let mut nearest_enemy = dist_max;
+            let mut visible_enemies = 0;
+            for vehicle in living_vehicles.iter().filter(|vehicle| is_enemy(vehicle)) {
+                let dist = vehicle.pos.distance(pos);
+                nearest_enemy = nearest_enemy.min(dist);
+                if dist < dist_max && self.map.is_wall_trace(pos, vehicle.pos).is_none() {
+                    visible_enemies += 1;
+                }
+            }
+
+            let projectile_radius2 = self.cvars.g_spawn_projectile_radius.powi(2);
+            let projectiles = self
+                .gs
+                .projectiles
+                .iter()
+                .filter(|(_, projectile)| projectile.pos.distance_squared(pos) < projectile_radius2)
+                .count();
+
+            let death_dist = death_pos.map_or(dist_max, |death_pos| death_pos.distance(pos));
+
+            let score = self.cvars.g_spawn_weight_enemy_distance * nearest_enemy / dist_max
+                - self.cvars.g_spawn_weight_enemy_visible * visible_enemies as f64
+                - self.cvars.g_spawn_weight_projectile * projectiles as f64
+                + self.cvars.g_spawn_weight_death_distance * death_dist.min(dist_max) / dist_max
+                + self.cvars.g_spawn_weight_random * self.sg.rng.gen::<f64>();
+            if best.map_or(true, |(_, best_score)| score > best_score) {
+                best = Some((spawn, score));
+            }
+        }
+
+        let (spawn, _) = best.or(fallback).unwrap();
+        (self.map.tile_center(spawn), self.map[spawn].angle)
+    }
+
     pub fn spawn_vehicle(&mut self, player_handle: Index, use_spawns: bool) {
-        let veh_type = VehicleType::from_repr(self.sg.rng.gen_range(0..3)).unwrap();
+        let veh_type = self.pick_vehicle_type(player_handle);
         let (spawn_pos, spawn_angle) = if use_spawns {
-            self.map.random_spawn(&mut self.sg.rng)
+            self.pick_spawn(player_handle)
         } else {
             let (pos, _angle) = self.map.random_nonwall(&mut self.sg.rng);
             // Most grass tiles have no rotation so everyone ends up facing right which looks bad.
@@ -78,6 +432,7 @@ impl ServerFrameCtx<'_> {
             turret_angle_current: vehicle.turret_angle_current,
             turret_angle_wanted: vehicle.turret_angle_wanted,
             spawn_time: vehicle.spawn_time,
+            protection_end: vehicle.protection_end,
             owner: vehicle.owner.slot(),
         };
         let msg = ServerMessage::SpawnVehicle(init);
@@ -105,18 +460,20 @@ impl ServerFrameCtx<'_> {
                 self.cvars.g_self_destruct_damage_edge,
                 self.cvars.g_self_destruct_radius,
                 Some(vehicle_handle),
+                DamageCause::SelfDestruct,
             );
 
             // 3) the player vehicle to create the small explosion on top.
-            self.damage(owner, vehicle_handle, f64::MAX);
+            self.damage(owner, vehicle_handle, f64::MAX, DamageCause::SelfDestruct);
 
             // LATER What was the order of explosions in the original RecWar? Make it configurable?
         }
     }
 
     pub fn sys_vehicle_movement(&mut self) {
-        for (_, vehicle) in self.gs.vehicles.iter_mut() {
-            let stats = self.cvars.g_vehicle_movement_stats(vehicle.veh_type);
+        let dt = self.gs.dt;
+        for vehicle_handle in self.gs.vehicles.collect_handles() {
+            let vehicle = &self.gs.vehicles[vehicle_handle];
 
             // No movement after death or when guiding
             let input = if vehicle.destroyed() {
@@ -124,106 +481,85 @@ impl ServerFrameCtx<'_> {
             } else {
                 let player = &self.gs.players[vehicle.owner];
                 if player.guided_missile.is_some() {
-                    self.gs.players[vehicle.owner].input.vehicle_while_guiding()
+                    player.input.vehicle_while_guiding()
                 } else {
-                    self.gs.players[vehicle.owner].input
+                    player.input
                 }
             };
-            let new_angle = Self::turning(
-                &stats,
-                &mut vehicle.vel,
-                &vehicle.angle,
-                &mut vehicle.turn_rate,
-                input,
-                self.gs.dt,
-            );
 
-            if vehicle
-                .hitbox
-                .corners(vehicle.pos, new_angle)
-                .iter()
-                .any(|&corner| self.map.is_wall(corner))
-            {
-                vehicle.turn_rate *= -0.5;
-            } else {
-                vehicle.angle = new_angle;
-            }
+            self.vehicle_movement(vehicle_handle, input, dt);
+        }
+    }
 
-            Self::accel_decel(&stats, &mut vehicle.vel, vehicle.angle, input, self.gs.dt);
+    pub fn sys_vehicle_collisions(&mut self) {
+        if !self.cvars.g_vehicle_collisions {
+            return;
+        }
 
-            let new_pos = vehicle.pos + vehicle.vel * self.gs.dt;
-            if vehicle
-                .hitbox
-                .corners(new_pos, vehicle.angle)
-                .iter()
-                .any(|&corner| self.map.is_wall(corner))
-            {
-                // LATER map edge in original RW absorbs the impact - there's no bounce
-                vehicle.vel *= -0.5;
-            } else {
-                vehicle.pos = new_pos;
+        let handles = self.gs.vehicles.collect_handles();
+        for (i, &handle1) in handles.iter().enumerate() {
+            for &handle2 in &handles[i + 1..] {
+                self.vehicle_collision(handle1, handle2);
             }
         }
     }
 
-    fn turning(
-        stats: &MovementStats,
-        vel: &mut Vec2f,
-        angle: &f64,
-        turn_rate: &mut f64,
-        input: NetInput,
-        dt: f64,
-    ) -> f64 {
-        let tr_change = input.right_left() * stats.turn_rate_increase * dt;
-        *turn_rate += tr_change;
-
-        // Friction's constant component - always the same no matter the speed
-        let tr_fric_const = stats.turn_rate_friction_const * dt;
-        if *turn_rate >= 0.0 {
-            *turn_rate = (*turn_rate - tr_fric_const).max(0.0);
-        } else {
-            *turn_rate = (*turn_rate + tr_fric_const).min(0.0);
+    fn vehicle_collision(&mut self, handle1: Index, handle2: Index) {
+        let vehicle1 = &self.gs.vehicles[handle1];
+        let vehicle2 = &self.gs.vehicles[handle2];
+
+        if vehicle1.destroyed() && vehicle2.destroyed() {
+            return;
+        }
+        if (vehicle1.destroyed() || vehicle2.destroyed()) && !self.cvars.g_vehicle_collisions_wrecks
+        {
+            return;
         }
 
-        // Friction's linear component - increases with speed
-        let tr_new = *turn_rate * (1.0 - stats.turn_rate_friction_linear).powf(dt);
-        *turn_rate = tr_new.clamped(-stats.turn_rate_max, stats.turn_rate_max);
+        let Some((normal, depth)) = vehicle1.hitbox.collision(
+            vehicle1.pos,
+            vehicle1.angle,
+            vehicle2.hitbox,
+            vehicle2.pos,
+            vehicle2.angle,
+        ) else {
+            return;
+        };
 
-        // A dirty hack to approximate car steering (i.e. no turning when still, reversed when moving backwards).
-        let steering_coef = if stats.steering_car > 0.0 {
-            let sign = angle.to_vec2f().dot(*vel).signum();
-            // Steering when below this speed is less effective.
-            let steering_speed = vel
-                .magnitude()
-                .clamped(-stats.steering_car, stats.steering_car);
-            steering_speed * sign / stats.steering_car
+        // Wrecks have infinite mass.
+        let inv_mass = |vehicle: &Vehicle| {
+            if vehicle.destroyed() {
+                0.0
+            } else {
+                1.0 / self.cvars.g_vehicle_mass(vehicle.veh_type)
+            }
+        };
+        let inv_mass1 = inv_mass(vehicle1);
+        let inv_mass2 = inv_mass(vehicle2);
+        let inv_mass_sum = inv_mass1 + inv_mass2;
+
+        // Momentum exchange - only when they're moving towards each other,
+        // otherwise they'd stick together while separating.
+        let vel_rel = (vehicle2.vel - vehicle1.vel).dot(normal);
+        let impulse = if vel_rel < 0.0 {
+            -(1.0 + self.cvars.g_vehicle_collision_restitution) * vel_rel / inv_mass_sum
         } else {
-            1.0
+            0.0
         };
 
-        // Turning - part of vel gets rotated to simulate steering
-        let turn = *turn_rate * dt * steering_coef;
-        let vel_rotation = turn * stats.turn_effectiveness;
-        vel.rotate_z(vel_rotation);
-
-        // Normalize to 0..=360 deg
-        (angle + turn).rem_euclid(2.0 * PI)
-    }
-
-    fn accel_decel(stats: &MovementStats, vel: &mut Vec2f, angle: f64, input: NetInput, dt: f64) {
-        let vel_change =
-            (input.up() * stats.accel_forward - input.down() * stats.accel_backward) * dt;
-        *vel += angle.to_vec2f() * vel_change;
-
-        // Friction's constant component - always the same no matter the speed
-        let vel_fric_const = stats.friction_const * dt;
-        let vel_norm = vel.try_normalized().unwrap_or_default();
-        *vel -= (vel_fric_const).min(vel.magnitude()) * vel_norm;
-
-        // Friction's linear component - increases with speed
-        *vel *= (1.0 - stats.friction_linear).powf(dt);
-        if vel.magnitude_squared() > stats.speed_max.powi(2) {
-            *vel = vel_norm * stats.speed_max;
+        // Push them apart so the lighter one moves more.
+        // A vehicle that would get pushed into a wall stays in place,
+        // the overlap will get resolved over the next frames.
+        let push1 = -normal * depth * inv_mass1 / inv_mass_sum;
+        let push2 = normal * depth * inv_mass2 / inv_mass_sum;
+
+        for (handle, push, vel_change) in [
+            (handle1, push1, -normal * impulse * inv_mass1),
+            (handle2, push2, normal * impulse * inv_mass2),
+        ] {
+            let vehicle = &mut self.gs.vehicles[handle];
+            vehicle.vel += vel_change;
+            FrameCtx::try_move(self.map, vehicle, vehicle.pos + push);
         }
     }
 
@@ -281,7 +617,8 @@ impl ServerFrameCtx<'_> {
 
     pub fn sys_shooting(&mut self) {
         let mut new_projectiles = Vec::new();
-        for (_, vehicle) in self.gs.vehicles.iter_mut() {
+        let mut protection_ended = Vec::new();
+        for (vehicle_handle, vehicle) in self.gs.vehicles.iter_mut() {
             let player = &mut self.gs.players[vehicle.owner];
             // Note: vehicles can shoot while controlling a missile
             if vehicle.destroyed() || !player.input.fire {
@@ -294,6 +631,12 @@ impl ServerFrameCtx<'_> {
                     continue;
                 }
 
+                // Not using `protected()` because `ammo` borrows the vehicle.
+                if self.gs.game_time < vehicle.protection_end {
+                    vehicle.protection_end = self.gs.game_time;
+                    protection_ended.push(vehicle_handle);
+                }
+
                 *ready_time = self.gs.game_time + self.cvars.g_weapon_refire(player.cur_weapon);
                 *count -= 1;
                 if *count == 0 {
@@ -329,6 +672,7 @@ impl ServerFrameCtx<'_> {
                     explode_time: f64::MAX,
                     owner: vehicle.owner,
                     target: None,
+                    hit_vehicles: Vec::new(),
                 };
 
                 match player.cur_weapon {
@@ -436,6 +780,114 @@ impl ServerFrameCtx<'_> {
             let msg = ServerMessage::SpawnProjectile(spawn);
             self.net_send_all(msg);
         }
+
+        for vehicle_handle in protection_ended {
+            let msg = ServerMessage::ProtectionEnd {
+                index: vehicle_handle.slot(),
+            };
+            self.net_send_all(msg);
+        }
+    }
+
+    pub fn sys_mines(&mut self) {
+        // Dropping
+        for vehicle_handle in self.gs.vehicles.collect_handles() {
+            let vehicle = &self.gs.vehicles[vehicle_handle];
+            let player = &self.gs.players[vehicle.owner];
+            if vehicle.destroyed() || player.input_prev.mine || !player.input.mine {
+                continue;
+            }
+
+            let owner = vehicle.owner;
+            let count = self
+                .gs
+                .mines
+                .iter()
+                .filter(|(_, mine)| mine.owner == owner)
+                .count();
+            if count >= self.cvars.g_mine_limit {
+                continue;
+            }
+
+            let back = vehicle.hitbox.mins.x - self.cvars.g_mine_offset_back;
+            let pos = vehicle.pos + Vec2f::new(back, 0.0).rotated_z(vehicle.angle);
+            if self.map.is_wall(pos) {
+                continue;
+            }
+
+            let arm_time = self.gs.game_time + self.cvars.g_mine_arming_delay;
+            let mine_handle = self.gs.mines.insert(Mine::new(pos, arm_time, owner));
+
+            let init = MineInit {
+                index: mine_handle.slot(),
+                pos,
+                arm_time,
+                owner: owner.slot(),
+            };
+            self.net_send_all(ServerMessage::SpawnMine(init));
+
+            // Attacking in any way ends spawn protection, same as shooting.
+            let vehicle = &mut self.gs.vehicles[vehicle_handle];
+            if vehicle.protected(self.gs.game_time) {
+                vehicle.protection_end = self.gs.game_time;
+                let msg = ServerMessage::ProtectionEnd {
+                    index: vehicle_handle.slot(),
+                };
+                self.net_send_all(msg);
+            }
+        }
+
+        // Triggering
+        let radius2 = self.cvars.g_mine_trigger_radius.powi(2);
+        for mine_handle in self.gs.mines.collect_handles() {
+            let mine = &self.gs.mines[mine_handle];
+            let mut owner_left = mine.owner_left;
+            let mut triggered = false;
+            for (_, vehicle) in &self.gs.vehicles {
+                if vehicle.destroyed() || self.is_friendly_fire(mine.owner, vehicle.owner) {
+                    continue;
+                }
+
+                let inside = vehicle.pos.distance_squared(mine.pos) <= radius2;
+                if vehicle.owner == mine.owner && !owner_left {
+                    owner_left = !inside;
+                    continue;
+                }
+                if inside && self.gs.game_time >= mine.arm_time {
+                    triggered = true;
+                }
+            }
+
+            let mine = &mut self.gs.mines[mine_handle];
+            mine.owner_left = owner_left;
+            if triggered {
+                self.mine_explode(mine_handle);
+            }
+        }
+    }
+
+    fn mine_explode(&mut self, mine_handle: Index) {
+        let mine = self.gs.mines.remove(mine_handle).unwrap();
+
+        let msg = ServerMessage::DestroyMine {
+            index: mine_handle.slot(),
+        };
+        self.net_send_all(msg);
+
+        let expl_scale = self.cvars.g_mine_explosion_scale;
+        self.spawn_explosion(mine.pos, expl_scale, false);
+
+        let expl_damage = expl_scale * self.cvars.g_mine_explosion_damage;
+        let expl_radius = expl_scale * self.cvars.g_mine_explosion_radius;
+        self.explosion_damage(
+            mine.owner,
+            mine.pos,
+            expl_damage,
+            expl_damage,
+            expl_radius,
+            None,
+            DamageCause::Mine,
+        );
     }
 
     fn hm_forget(hm_handle: Index, hm: &mut Projectile, target: &mut Vehicle) {
@@ -487,11 +939,15 @@ impl ServerFrameCtx<'_> {
                 let mut best_target = None;
                 let mut best_target_angle_diff = f64::INFINITY;
 
+                let hm_team = self.gs.players[hm.owner].team;
                 for (vehicle_handle, vehicle) in self.gs.vehicles.iter() {
                     if vehicle.owner == hm.owner || vehicle.destroyed() {
                         // LATER Allow targetting self if the missile loops around other vehicles
                         continue;
                     }
+                    if hm_team.is_some() && hm_team == self.gs.players[vehicle.owner].team {
+                        continue;
+                    }
 
                     let target_dir = (vehicle.pos - hm.pos).normalized();
                     let dot = forward_dir.dot(target_dir);
@@ -530,7 +986,7 @@ impl ServerFrameCtx<'_> {
 
             // Movement
             let stats = self.cvars.g_homing_missile_movement_stats();
-            hm.angle = Self::turning(
+            hm.angle = FrameCtx::turning(
                 &stats,
                 &mut hm.vel,
                 &hm.angle,
@@ -538,7 +994,7 @@ impl ServerFrameCtx<'_> {
                 input,
                 self.gs.dt,
             );
-            Self::accel_decel(&stats, &mut hm.vel, hm.angle, input, self.gs.dt);
+            FrameCtx::accel_decel(&stats, &mut hm.vel, hm.angle, input, self.gs.dt);
         }
     }
 
@@ -560,7 +1016,7 @@ impl ServerFrameCtx<'_> {
                 NetInput::new_up()
             };
 
-            gm.angle = Self::turning(
+            gm.angle = FrameCtx::turning(
                 &stats,
                 &mut gm.vel,
                 &gm.angle,
@@ -569,7 +1025,7 @@ impl ServerFrameCtx<'_> {
                 self.gs.dt,
             );
 
-            Self::accel_decel(&stats, &mut gm.vel, gm.angle, input, self.gs.dt);
+            FrameCtx::accel_decel(&stats, &mut gm.vel, gm.angle, input, self.gs.dt);
         }
     }
 
@@ -613,46 +1069,63 @@ impl ServerFrameCtx<'_> {
                 self.net_send_all(msg);
             }
 
+            // Find all vehicles in the path first so they can be hit in order.
+            let mut hits = Vec::new();
             for vehicle_handle in self.gs.vehicles.collect_handles() {
-                // LATER immediately killing vehicles here means 2 players can't share a kill
-                let vehicle = &mut self.gs.vehicles[vehicle_handle];
-
                 // borrowck dance - reborrow each iteration of the loop
                 // so the borrow ends before we pass `gs` to other functions.
+                let vehicle = &self.gs.vehicles[vehicle_handle];
                 let projectile = &self.gs.projectiles[proj_handle];
 
                 if vehicle.destroyed()
                     || vehicle.owner == projectile.owner
-                    || (is_rail && self.gs.rail_hits.get(&proj_handle) == Some(&vehicle_handle))
+                    || projectile.hit_vehicles.contains(&vehicle_handle)
                 {
                     continue;
                 }
 
-                let nearest_point = step.projected_point(vehicle.pos);
-                let dist2 = nearest_point.distance_squared(vehicle.pos);
-                if dist2 <= self.cvars.g_hitcircle_radius * self.cvars.g_hitcircle_radius {
-                    if self.cvars.d_tracing {
-                        dbg_cross!(nearest_point, 0.5);
-                    }
-                    let dmg = self.cvars.g_weapon_damage_direct(projectile.weapon);
-
-                    if is_rail {
-                        self.gs.rail_hits.insert(proj_handle, vehicle_handle);
-                        vehicle.vel += step_dir * self.cvars.g_railgun_push;
-                    }
-
-                    let attacker_handle = projectile.owner;
-                    self.damage(attacker_handle, vehicle_handle, dmg);
-                    if !is_rail {
-                        self.projectile_impact(proj_handle, nearest_point);
-                        break; // LATER actually ... what if the segment is long and 2 vehicles are in the path
-                    }
+                let hit_point =
+                    vehicle
+                        .hitbox
+                        .segment_hit(vehicle.pos, vehicle.angle, step.start, step.end);
+                if let Some(hit_point) = hit_point {
+                    hits.push((vehicle_handle, hit_point));
                 } else if projectile.weapon == Weapon::Bfg
                     && weapons::bfg_beam_hit(self.cvars, self.map, projectile.pos, vehicle.pos)
                 {
                     let dmg = self.cvars.g_bfg_beam_damage_per_sec * self.gs.dt;
                     let attacker_handle = projectile.owner;
-                    self.damage(attacker_handle, vehicle_handle, dmg);
+                    let cause = DamageCause::Weapon(Weapon::Bfg);
+                    self.damage(attacker_handle, vehicle_handle, dmg, cause);
+                }
+            }
+            hits.sort_by(|(_, a), (_, b)| {
+                let dist_a = a.distance_squared(step.start);
+                let dist_b = b.distance_squared(step.start);
+                dist_a.total_cmp(&dist_b)
+            });
+
+            // Rails pierce and hit every vehicle in the path, everything else only the nearest one.
+            // LATER immediately killing vehicles here means 2 players can't share a kill
+            for (vehicle_handle, hit_point) in hits {
+                if self.cvars.d_tracing {
+                    dbg_cross!(hit_point, 0.5);
+                }
+
+                let projectile = &mut self.gs.projectiles[proj_handle];
+                let dmg = self.cvars.g_weapon_damage_direct(projectile.weapon);
+                let attacker_handle = projectile.owner;
+                let cause = DamageCause::Weapon(projectile.weapon);
+
+                if is_rail {
+                    projectile.hit_vehicles.push(vehicle_handle);
+                    self.gs.vehicles[vehicle_handle].vel += step_dir * self.cvars.g_railgun_push;
+                }
+
+                self.damage(attacker_handle, vehicle_handle, dmg, cause);
+                if !is_rail {
+                    self.projectile_impact(proj_handle, hit_point);
+                    break;
                 }
             }
 
@@ -662,20 +1135,37 @@ impl ServerFrameCtx<'_> {
                 // We could set a flag when hitting vehicles above instead of `.contains` but this is more future-proof.
                 if self.gs.projectiles.contains(proj_handle) {
                     self.projectile_impact(proj_handle, hit_pos);
-                    if is_rail {
-                        self.gs.rail_hits.remove(&proj_handle);
-                    }
                 }
             }
         }
     }
 
-    pub fn damage(&mut self, attacker_handle: Index, vehicle_handle: Index, dmg_amount: f64) {
-        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+    pub fn damage(
+        &mut self,
+        attacker_handle: Index,
+        vehicle_handle: Index,
+        dmg_amount: f64,
+        cause: DamageCause,
+    ) {
+        let vehicle = &self.gs.vehicles[vehicle_handle];
 
         soft_assert!(!vehicle.destroyed());
 
-        vehicle.hp_fraction -= dmg_amount / self.cvars.g_vehicle_hp(vehicle.veh_type);
+        if vehicle.protected(self.gs.game_time)
+            || self.is_friendly_fire(attacker_handle, vehicle.owner)
+        {
+            return;
+        }
+
+        let vehicle = &mut self.gs.vehicles[vehicle_handle];
+
+        let hp = self.cvars.g_vehicle_hp(vehicle.veh_type);
+        let dealt = dmg_amount.min(vehicle.hp_fraction * hp);
+        self.sg
+            .stats
+            .add_damage(vehicle_handle, cause, dealt, self.gs.game_time);
+
+        vehicle.hp_fraction -= dmg_amount / hp;
 
         // Not using 0.0 here because of floating point errors.
         // Some weapons should reduce health to exact 0 in a small number of hits but it ends up being a tiny bit above it.
@@ -688,6 +1178,21 @@ impl ServerFrameCtx<'_> {
         vehicle.hp_fraction = 0.0;
         let veh_owner = vehicle.owner; // Borrowck
         let veh_pos = vehicle.pos; // Borrowck
+        let veh_type = vehicle.veh_type; // Borrowck
+
+        // Suicides don't count as a fight won by the vehicle type.
+        let attacker_type = self.gs.players[attacker_handle]
+            .vehicle
+            .filter(|_| attacker_handle != veh_owner)
+            .map(|handle| self.gs.vehicles[handle].veh_type);
+        self.sg.stats.add_kill(
+            vehicle_handle,
+            cause,
+            self.gs.game_time,
+            attacker_type,
+            veh_type,
+        );
+
         self.spawn_explosion(veh_pos, 1.0, false);
         self.gs.players[veh_owner].guided_missile = None; // No guiding after death
 
@@ -701,6 +1206,7 @@ impl ServerFrameCtx<'_> {
 
         let victim = &mut self.gs.players[veh_owner];
         victim.death_time = self.gs.game_time;
+        victim.death_pos = Some(veh_pos);
 
         self.update_score_kill(attacker_handle, veh_owner);
 
@@ -751,7 +1257,15 @@ impl ServerFrameCtx<'_> {
         let expl_damage = expl_scale * self.cvars.g_weapon_explosion_damage(weapon);
         let expl_radius = expl_scale * self.cvars.g_weapon_explosion_radius(weapon);
         if expl_damage > 0.0 || expl_radius > 0.0 {
-            self.explosion_damage(owner, hit_pos, expl_damage, expl_damage, expl_radius, None);
+            self.explosion_damage(
+                owner,
+                hit_pos,
+                expl_damage,
+                expl_damage,
+                expl_radius,
+                None,
+                DamageCause::Weapon(weapon),
+            );
         }
 
         if weapon == Weapon::Hm {
@@ -791,6 +1305,7 @@ impl ServerFrameCtx<'_> {
         damage_edge: f64,
         radius: f64,
         ignore: Option<Index>,
+        cause: DamageCause,
     ) {
         if self.cvars.d_explosion_radius {
             dbg_line!(expl_pos, expl_pos + Vec2f::new(radius, 0.0), 5.0);
@@ -804,16 +1319,30 @@ impl ServerFrameCtx<'_> {
             }
 
             let vehicle = &self.gs.vehicles[vehicle_handle];
-            if vehicle.destroyed() {
+            if vehicle.destroyed() || self.is_friendly_fire(owner, vehicle.owner) {
                 continue;
             }
 
-            let center_dist = (vehicle.pos - expl_pos).magnitude();
-            let dist = (center_dist - self.cvars.g_hitcircle_radius).max(0.0);
+            let dist = vehicle
+                .hitbox
+                .distance(vehicle.pos, vehicle.angle, expl_pos);
             if dist < radius {
                 let expl_damage = lerp_ranges(0.0, radius, damage_center, damage_edge, dist);
-                self.damage(owner, vehicle_handle, expl_damage);
+                self.damage(owner, vehicle_handle, expl_damage, cause);
             }
         }
     }
+
+    /// Whether damage from the attacker to the victim should be ignored because they're teammates.
+    ///
+    /// Damaging yourself is never considered friendly fire.
+    fn is_friendly_fire(&self, attacker_handle: Index, victim_handle: Index) -> bool {
+        if self.cvars.g_friendly_fire || attacker_handle == victim_handle {
+            return false;
+        }
+
+        let attacker_team = self.gs.players[attacker_handle].team;
+        let victim_team = self.gs.players[victim_handle].team;
+        attacker_team.is_some() && attacker_team == victim_team
+    }
 }
@@ -2,7 +2,7 @@
 
 use crate::prelude::*;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
 pub struct CommonTimings {
     pub update_durations_avg: f64,
     pub update_durations_max: f64,
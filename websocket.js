@@ -0,0 +1,68 @@
+// WebSocket connections for the browser build, the Rust side is in src/net/websocket.rs.
+// Browsers can't open raw TCP or UDP sockets so this is the only way to connect to a server.
+
+"use strict";
+
+const rw_sockets = [];
+
+// Not cached because the view becomes invalid when WASM memory grows.
+function rw_ws_bytes(ptr, len) {
+    return new Uint8Array(wasm_memory.buffer, ptr, len);
+}
+
+miniquad_add_plugin({
+    name: "rec_wars_websocket",
+    version: 1,
+    register_plugin: function (importObject) {
+        importObject.env.rw_ws_connect = function (url_ptr, url_len) {
+            const url = new TextDecoder().decode(rw_ws_bytes(url_ptr, url_len));
+            const socket = {
+                ws: new WebSocket(url),
+                // Messages sent before the connection opened.
+                pending: [],
+                received: [],
+                closed: false,
+            };
+            socket.ws.binaryType = "arraybuffer";
+            socket.ws.onopen = function () {
+                for (const data of socket.pending) {
+                    socket.ws.send(data);
+                }
+                socket.pending = [];
+            };
+            socket.ws.onmessage = function (event) {
+                socket.received.push(new Uint8Array(event.data));
+            };
+            socket.ws.onclose = function () {
+                socket.closed = true;
+            };
+            socket.ws.onerror = function () {
+                socket.closed = true;
+            };
+            rw_sockets.push(socket);
+            return rw_sockets.length - 1;
+        };
+        importObject.env.rw_ws_send = function (id, ptr, len) {
+            const socket = rw_sockets[id];
+            // Copy because the WASM memory will be reused.
+            const data = rw_ws_bytes(ptr, len).slice();
+            if (socket.ws.readyState === WebSocket.CONNECTING) {
+                socket.pending.push(data);
+            } else {
+                socket.ws.send(data);
+            }
+        };
+        importObject.env.rw_ws_closed = function (id) {
+            return rw_sockets[id].closed ? 1 : 0;
+        };
+        importObject.env.rw_ws_next_len = function (id) {
+            const received = rw_sockets[id].received;
+            return received.length > 0 ? received[0].length : -1;
+        };
+        importObject.env.rw_ws_take = function (id, ptr) {
+            const data = rw_sockets[id].received.shift();
+            rw_ws_bytes(ptr, data.length).set(data);
+        };
+    },
+    on_init: function () {},
+});